@@ -1,6 +1,12 @@
 use engine::bitboard::*;
+use engine::movegen::magic;
+use engine::position::Position;
+use engine::search::{self, SearchLimits};
 use engine::*;
-use engine::{fen, fen_log};
+use engine::{chess960, fen, fen_log, perft, pgn};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{cmp::Ordering, env, process::exit};
 
 #[macro_export]
@@ -25,7 +31,13 @@ fn main() {
                 if args.len() == 3 {
                     let fen = &args[2];
 
-                    let mut game_state = fen::return_state(fen);
+                    let mut game_state = match fen::return_state(fen) {
+                        Ok(game_state) => game_state,
+                        Err(err) => {
+                            fen_log!("Invalid FEN string: {}", err);
+                            exit(1);
+                        }
+                    };
                     fen::print_all(&game_state);
                     println!();
                     let pieces = convert(&mut game_state.pieces);
@@ -37,6 +49,35 @@ fn main() {
             "-d" | "--default" => {
                 fen::print_all(&fen::default());
             }
+            "perft" => {
+                if args.get(2).map(String::as_str) == Some("--suite") {
+                    suite(&args[3..]);
+                } else {
+                    perft(&args[2..], false);
+                }
+            }
+            "divide" => {
+                perft(&args[2..], true);
+            }
+            "magics" => {
+                magics();
+            }
+            "validate" => {
+                validate();
+            }
+            "pgn" => match args.get(2) {
+                Some(path) => read_pgn(path, args.get(3).map(String::as_str) == Some("--export")),
+                None => {
+                    main_log!("Error: missing PGN file");
+                    exit(1);
+                }
+            },
+            "epd" => {
+                epd(&args[2..]);
+            }
+            "960" => {
+                chess960(&args[2..]);
+            }
             _ => {
                 main_log!("Invalid option '{}'.", args[1]);
                 exit(1);
@@ -48,6 +89,383 @@ fn main() {
     }
 }
 
+// perft options, parsed from "<depth> [--fen <FEN_STRING>] [--threads <N>] [--hash <MB>]"
+struct PerftArgs {
+    depth: u32,
+    position: Position,
+    threads: usize,
+    hash_mb: usize,
+}
+
+fn option_value<T: std::str::FromStr>(args: &[String], i: usize) -> T {
+    match args.get(i + 1).map(|value| value.parse::<T>()) {
+        Some(Ok(value)) => value,
+        _ => {
+            main_log!("Error: missing or invalid value for '{}'", args[i]);
+            exit(1);
+        }
+    }
+}
+
+fn perft_args(args: &[String]) -> PerftArgs {
+    let depth = match args.first().map(|depth| depth.parse::<u32>()) {
+        Some(Ok(depth)) => depth,
+        _ => {
+            main_log!("Error: missing or invalid depth");
+            exit(1);
+        }
+    };
+
+    let mut fen = fen::START_POS.to_string();
+    let mut threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut hash_mb = 0;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-f" | "--fen" => match args.get(i + 1) {
+                Some(value) => fen = value.clone(),
+                None => {
+                    fen_log!("Error: missing FEN string");
+                    exit(1);
+                }
+            },
+            "-t" | "--threads" => threads = option_value(args, i),
+            "--hash" => hash_mb = option_value(args, i),
+            arg => {
+                main_log!("Invalid option '{}'.", arg);
+                exit(1);
+            }
+        }
+        i += 2;
+    }
+
+    let position = match Position::from_fen(&fen) {
+        Ok(position) => position,
+        Err(err) => {
+            fen_log!("Invalid FEN string: {}", err);
+            exit(1);
+        }
+    };
+
+    PerftArgs { depth, position, threads, hash_mb }
+}
+
+fn perft(args: &[String], divide: bool) {
+    let args = perft_args(args);
+    let start = Instant::now();
+    let table = (args.hash_mb > 0).then(|| perft::PerftTable::new(args.hash_mb));
+
+    let nodes = if divide {
+        let counts = perft::divide(&args.position, args.depth, args.threads, table.as_ref());
+        for (mv, nodes) in &counts {
+            println!("{}: {}", mv.to_uci(args.position.chess960), nodes);
+        }
+        println!();
+        counts.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft::perft_parallel(&args.position, args.depth, args.threads, table.as_ref())
+    };
+
+    let elapsed = start.elapsed();
+    println!("Nodes: {}", nodes);
+    println!("Time: {:.3}s", elapsed.as_secs_f64());
+    println!("NPS: {:.0}", nodes as f64 / elapsed.as_secs_f64().max(1e-9));
+}
+
+fn suite(args: &[String]) {
+    let max_depth = match args.first().map(|depth| depth.parse::<u32>()) {
+        Some(Ok(depth)) => depth,
+        Some(Err(_)) => {
+            main_log!("Error: invalid depth '{}'", args[0]);
+            exit(1);
+        }
+        None => 4,
+    };
+
+    let mismatches = perft::run_suite(max_depth, |result| {
+        if result.is_ok() {
+            println!(
+                "\x1b[32mok\x1b[0m   {} depth {}: {}",
+                result.name, result.depth, result.nodes
+            );
+        } else {
+            println!(
+                "\x1b[31mfail\x1b[0m {} depth {}: {} (expected {})",
+                result.name, result.depth, result.nodes, result.expected
+            );
+        }
+    });
+
+    println!();
+    if mismatches.is_empty() {
+        main_log!("All perft counts match.");
+    } else {
+        main_log!("{} mismatch(es).", mismatches.len());
+        exit(1);
+    }
+}
+
+// check FEN strings read line by line from stdin, exits with 1 if any of them is invalid
+fn validate() {
+    let mut invalid = 0;
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                main_log!("Error reading stdin: {}", err);
+                exit(1);
+            }
+        };
+        let fen = line.trim();
+        if fen.is_empty() {
+            continue;
+        }
+
+        let problems = match Position::from_fen(fen) {
+            Ok(position) => position.validate().iter().map(|problem| problem.to_string()).collect(),
+            Err(err) => vec![err.to_string()],
+        };
+
+        if problems.is_empty() {
+            println!("\x1b[32mok\x1b[0m      {}", fen);
+        } else {
+            invalid += 1;
+            println!("\x1b[31minvalid\x1b[0m {}", fen);
+            for problem in problems {
+                println!("        - {}", problem);
+            }
+        }
+    }
+
+    if invalid > 0 {
+        exit(1);
+    }
+}
+
+// replay every game of a PGN file, reporting the ones that cannot be read, and optionally print
+// the games that can in export format
+fn read_pgn(path: &str, export: bool) {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            main_log!("Error: cannot open '{}': {}", path, err);
+            exit(1);
+        }
+    };
+
+    let (mut games, mut moves, mut skipped) = (0, 0, 0);
+    for game in pgn::PgnReader::new(BufReader::new(file)) {
+        match game {
+            Ok(game) => {
+                games += 1;
+                moves += game.moves.len();
+                if export {
+                    println!("{}", game);
+                }
+            }
+            Err(err) => {
+                skipped += 1;
+                main_log!("Skipped {}", err);
+            }
+        }
+    }
+
+    if !export {
+        main_log!("{} games ({} moves) read, {} skipped.", games, moves, skipped);
+    }
+    if skipped > 0 {
+        exit(1);
+    }
+}
+
+// search every position of an EPD file for a fixed time and check the best move against its bm
+// and am opcodes, from "<FILE> [--time <MS>]"
+fn epd(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            main_log!("Error: missing EPD file");
+            exit(1);
+        }
+    };
+    let time = match args.get(1).map(String::as_str) {
+        Some("--time") => Duration::from_millis(option_value(args, 1)),
+        Some(arg) => {
+            main_log!("Invalid option '{}'.", arg);
+            exit(1);
+        }
+        None => Duration::from_secs(1),
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            main_log!("Error: cannot open '{}': {}", path, err);
+            exit(1);
+        }
+    };
+
+    let (mut tests, mut solved) = (0, 0);
+    let mut solution_time = Duration::ZERO;
+    let start = Instant::now();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                main_log!("Error reading '{}': {}", path, err);
+                exit(1);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = match fen::return_epd(&line) {
+            Ok(record) => record,
+            Err(err) => {
+                main_log!("Skipped line {}: {}", number + 1, err);
+                continue;
+            }
+        };
+        let id = record.id().map_or_else(|| format!("line {}", number + 1), str::to_string);
+        let position = Position::from(&record.state);
+
+        // the search needs both kings and a reachable position
+        let problems = position.validate();
+        if !problems.is_empty() {
+            let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+            main_log!("Skipped line {}: illegal position: {}", number + 1, problems.join(", "));
+            continue;
+        }
+
+        // moves in the opcodes that are not legal here make the test meaningless
+        let parse = |moves: &[String]| -> Result<Vec<_>, String> {
+            moves.iter().map(|san| position.parse_san(san).map_err(|err| err.to_string())).collect()
+        };
+        let (best, avoid) = match (parse(record.best_moves()), parse(record.avoid_moves())) {
+            (Ok(best), Ok(avoid)) if !best.is_empty() || !avoid.is_empty() => (best, avoid),
+            (Err(err), _) | (_, Err(err)) => {
+                main_log!("Skipped {}: {}", id, err);
+                continue;
+            }
+            _ => {
+                main_log!("Skipped {}: no bm or am opcode", id);
+                continue;
+            }
+        };
+        let passes = |mv| (best.is_empty() || best.contains(&mv)) && !avoid.contains(&mv);
+
+        // time of the first iteration from which on the best move stayed correct
+        let mut found = None;
+        let info =
+            search::search(&position, SearchLimits { depth: None, time: Some(time) }, |info| {
+                match info.best_move().filter(|mv| passes(*mv)) {
+                    Some(_) => found = found.or(Some(info.time)),
+                    None => found = None,
+                }
+            });
+
+        tests += 1;
+        let played = info.best_move().map_or("none".to_string(), |mv| position.to_san(mv));
+        match found {
+            Some(time) => {
+                solved += 1;
+                solution_time += time;
+                println!(
+                    "\x1b[32mok\x1b[0m   {}: {} in {:.3}s (depth {})",
+                    id,
+                    played,
+                    time.as_secs_f64(),
+                    info.depth
+                );
+            }
+            None => {
+                let mut expected = Vec::new();
+                if !record.best_moves().is_empty() {
+                    expected.push(format!("bm {}", record.best_moves().join(" ")));
+                }
+                if !record.avoid_moves().is_empty() {
+                    expected.push(format!("am {}", record.avoid_moves().join(" ")));
+                }
+                println!(
+                    "\x1b[31mfail\x1b[0m {}: {} (expected {})",
+                    id,
+                    played,
+                    expected.join(", ")
+                );
+            }
+        }
+    }
+
+    println!();
+    main_log!("Solved {}/{} in {:.1}s.", solved, tests, start.elapsed().as_secs_f64());
+    if solved > 0 {
+        main_log!("Average time to solution: {:.3}s.", solution_time.as_secs_f64() / solved as f64);
+    }
+}
+
+// seed for anything random, taken from the clock
+fn time_seed() -> u64 {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos());
+    time as u64 | 1
+}
+
+fn scharnagl_arg(arg: &str) -> u16 {
+    match arg.parse::<u16>() {
+        Ok(number) if number < chess960::POSITIONS => number,
+        _ => {
+            main_log!("Error: '{}' is not a Chess960 position number (0-959)", arg);
+            exit(1);
+        }
+    }
+}
+
+// Chess960 start positions: "[N]" (random if missing), "<WHITE> <BLACK>" or "--double" for
+// Double Fischer Random, and "--rank <BACK_RANK>" for the number of a back rank
+fn chess960(args: &[String]) {
+    let seed = time_seed();
+    let random = |shift: u32| ((seed >> shift) % chess960::POSITIONS as u64) as u16;
+
+    let (white, black) = match args.first().map(String::as_str) {
+        None => (random(0), random(0)),
+        Some("--double") => (random(0), random(20)),
+        Some("--rank") => {
+            let rank = args.get(1).and_then(|rank| chess960::parse_back_rank(rank));
+            match rank.and_then(|rank| chess960::scharnagl_number(&rank)) {
+                Some(number) => println!("{}", number),
+                None => {
+                    main_log!("Error: missing or invalid Chess960 back rank");
+                    exit(1);
+                }
+            }
+            return;
+        }
+        Some(white) => {
+            let white = scharnagl_arg(white);
+            (white, args.get(1).map_or(white, |black| scharnagl_arg(black)))
+        }
+    };
+
+    let position = chess960::start_position(white, black).expect("numbers are in range");
+    if white == black {
+        main_log!("Chess960 position {}", white);
+    } else {
+        main_log!("Double Fischer Random position {} (white) / {} (black)", white, black);
+    }
+    println!("{}", position.to_fen());
+}
+
+// search for new magic numbers and print them as Rust source
+fn magics() {
+    let seed = time_seed();
+
+    magic::print_magics("ROOK_MAGICS", &magic::generate_magics(magic::Slider::Rook, seed));
+    println!();
+    magic::print_magics("BISHOP_MAGICS", &magic::generate_magics(magic::Slider::Bishop, seed));
+}
+
 fn help() {
     let help_msg = format!(
         "\x1b[32m\x1b[1mKage \x1b[0m {}
@@ -62,8 +480,33 @@ fn help() {
     \x1b[32m-f, --fen <FEN_STRING>\x1b[0m
         Parse FEN string.
 
+\x1b[33mCOMMANDS:\x1b[0m
+    \x1b[32mperft <DEPTH> [--fen <FEN_STRING>] [--threads <N>] [--hash <MB>]\x1b[0m
+        Count leaf nodes of the move tree (start position by default), splitting root
+        moves across threads and optionally caching subtree counts in a hash table.
+    \x1b[32mdivide <DEPTH> [--fen <FEN_STRING>] [--threads <N>] [--hash <MB>]\x1b[0m
+        Perft with node counts per root move, counting each subtree like perft.
+    \x1b[32mperft --suite [MAX_DEPTH]\x1b[0m
+        Check reference positions against known node counts (depth 4 by default).
+    \x1b[32mmagics\x1b[0m
+        Generate new magic numbers for the rook and bishop attack tables.
+    \x1b[32m960 [N | <WHITE> <BLACK> | --double | --rank <BACK_RANK>]\x1b[0m
+        Chess960 start position by Scharnagl number (0-959, 518 is the classical setup),
+        random if no number is given. Two numbers or --double give Double Fischer Random,
+        --rank prints the number of a back rank such as RNBQKBNR.
+    \x1b[32mpgn <FILE> [--export]\x1b[0m
+        Read and replay every game of a PGN file, reporting malformed games. With --export
+        the games are printed again in PGN export format.
+    \x1b[32mepd <FILE> [--time <MS>]\x1b[0m
+        Search every position of an EPD test suite (1000 ms each by default) and report
+        which bm/am tests pass, the score and the time to solution.
+    \x1b[32mvalidate\x1b[0m
+        Read FEN strings from stdin (one per line) and report positions that cannot
+        arise in a legal game.
+
 \x1b[33mEXAMPLES:\x1b[0m
     kage --fen \"rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2 \"
+    kage divide 3 --fen \"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1\"
 
 Link: \x1b[4m\x1b[34mhttps://github.com/rv178/kage\x1b[0m",
         env!("CARGO_PKG_VERSION")
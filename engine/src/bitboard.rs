@@ -1,8 +1,11 @@
-use crate::{utils::match_u32_to_sq, Colour};
-use crate::{Piece, Square};
+use crate::Colour;
+use crate::{File, Piece, Rank, Square};
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
 use std::process::exit;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct BitBoard(pub u64);
 
 pub const UNIVERSAL: BitBoard = BitBoard(18446744073709551615);
@@ -11,6 +14,8 @@ pub const NOT_H_FILE: BitBoard = BitBoard(9187201950435737471);
 pub const NOT_HG_FILE: BitBoard = BitBoard(4557430888798830399);
 pub const NOT_AB_FILE: BitBoard = BitBoard(18229723555195321596);
 pub const EMPTY: BitBoard = BitBoard(0);
+// a8, c8, ... h1
+pub const LIGHT_SQUARES: BitBoard = BitBoard(12273903644374837845);
 
 // all ranks
 pub const RANKS: [BitBoard; 8] = [
@@ -74,7 +79,9 @@ pub const ANTI_DIAG: [BitBoard; 15] = [
     BitBoard(0x8000000000000000),
 ];
 
-const UNICODE_PIECES: [&str; 12] = ["♙", "♘", "♗", "♖", "♕", "♔", "♟", "♞", "♝", "♜", "♛", "♚"];
+// indexed by colour * 6 + kind, so black (index 0) uses the outlined glyphs; figurine SAN uses
+// them for both colours
+pub const UNICODE_PIECES: [&str; 12] = ["♙", "♘", "♗", "♖", "♕", "♔", "♟", "♞", "♝", "♜", "♛", "♚"];
 const ASCII_PIECES: [&str; 12] = ["p", "n", "b", "r", "q", "k", "P", "N", "B", "R", "Q", "K"];
 
 // convert piece list to array of bitboards
@@ -102,56 +109,33 @@ pub fn convert(pieces: &mut [Option<Piece>; 64]) -> [[BitBoard; 6]; 2] {
 
 // get all occupancies of pieces of a particular colour
 pub fn occupancies(pieces: [[BitBoard; 6]; 2], side: Colour) -> BitBoard {
-    let mut occ: BitBoard = BitBoard::empty();
+    let sides = match side {
+        Colour::White => &pieces[1..],
+        Colour::Black => &pieces[..1],
+        Colour::Undefined => &pieces[..],
+    };
 
-    match side {
-        Colour::White => {
-            for i in 0..6 {
-                occ.0 |= pieces[1][i].0;
-            }
-        }
-        Colour::Black => {
-            for i in 0..6 {
-                occ.0 |= pieces[0][i].0;
-            }
-        }
-        Colour::Undefined => {
-            for i in 0..2 {
-                for j in 0..6 {
-                    occ.0 |= pieces[i][j].0;
-                }
-            }
-        }
-    }
-
-    occ
+    sides.iter().flatten().fold(BitBoard::empty(), |occ, bb| occ | *bb)
 }
 
 // print chess board from array of bitboards
 pub fn print_bb_pieces(pieces: [[BitBoard; 6]; 2], unicode: bool) {
-    let mut x = 8;
+    // index into the piece symbols for every square
+    let mut board: [Option<usize>; 64] = [None; 64];
+    for (i, bb) in pieces.iter().flatten().enumerate() {
+        for sq in *bb {
+            board[sq as usize].get_or_insert(i);
+        }
+    }
+
     for rank in 0..8 {
-        x -= 1;
-        print!("\x1b[34m{}\x1b[0m  ", x + 1);
+        print!("\x1b[34m{}\x1b[0m  ", 8 - rank);
         for file in 0..8 {
-            let square = rank * 8 + file;
-            let mut piece: Option<u8> = None;
-
-            for (x, a) in pieces.iter().enumerate() {
-                for (y, b) in a.iter().enumerate() {
-                    if b.get_bit(match_u32_to_sq(square)) {
-                        let piece_num = y + x * 6;
-                        piece = Some(piece_num as u8);
-                        break;
-                    }
-                }
-            }
-
-            if let Some(piece) = piece {
+            if let Some(piece) = board[rank * 8 + file] {
                 if unicode {
-                    print!("{} ", UNICODE_PIECES[piece as usize]);
+                    print!("{} ", UNICODE_PIECES[piece]);
                 } else {
-                    print!("{} ", ASCII_PIECES[piece as usize]);
+                    print!("{} ", ASCII_PIECES[piece]);
                 }
             } else {
                 print!("\x1b[38;5;8m.\x1b[0m ");
@@ -178,52 +162,32 @@ impl BitBoard {
         self.0 == 0
     }
     // check if equal
+    #[deprecated(note = "compare with `==` instead")]
     pub fn is_eq(&self, rhs: Self) -> bool {
-        self.0 == rhs.0
+        *self == rhs
     }
     // set bit at given square (0 -> 1)
     pub fn set_bit(&mut self, square: Square) {
-        self.0 |= BitBoard::from_sq(square).0;
+        *self |= BitBoard::from_sq(square);
     }
     // toggle bit at given square
     pub fn toggle_bit(&mut self, square: Square) {
-        self.0 ^= BitBoard::from_sq(square).0;
+        *self ^= BitBoard::from_sq(square);
     }
     // get bit at given square
     pub fn get_bit(&self, square: Square) -> bool {
-        self.0 & BitBoard::from_sq(square).0 != 0
+        !(*self & BitBoard::from_sq(square)).is_empty()
     }
-    // pop bit at given square
+    // pop bit at given square (1 -> 0)
     pub fn pop_bit(&mut self, square: Square) {
-        self.0 &= !BitBoard::from_sq(square).0;
-
-        if self.get_bit(square) {
-            self.0 ^= BitBoard::from_sq(square).0;
-        } else {
-            self.0 = 0;
-        }
+        *self &= !BitBoard::from_sq(square);
     }
     // generate bitboard from piece list
     pub fn gen(pieces: &[Option<Piece>; 64], compare: char) -> Self {
-        let mut bin_str = String::new();
-        let mut pieces_rev = *pieces;
-        // reverse pieces array
-        pieces_rev.reverse();
-
-        for piece in pieces_rev {
-            if let Some(piece) = piece {
-                if piece.symbol != compare {
-                    bin_str.push('0');
-                } else {
-                    bin_str.push('1');
-                }
-            } else {
-                bin_str.push('0');
-            }
-        }
-
-        // convert binary string to decimal (u64)
-        Self(u64::from_str_radix(&bin_str, 2).unwrap())
+        Square::ALL
+            .into_iter()
+            .filter(|sq| pieces[*sq as usize].is_some_and(|piece| piece.symbol == compare))
+            .collect()
     }
 
     // generate bitboard of all pieces of one colour from piece list
@@ -269,23 +233,7 @@ impl BitBoard {
         println!("Hex: {:x}", self.0);
         println!("Value: {}", &self.0);
         println!();
-        let mut x = 8;
-        for rank in 0..8 {
-            print!("\x1b[34m{}\x1b[0m  ", x);
-            x -= 1;
-            for file in 0..8 {
-                let square = rank * 8 + file;
-                if self.0 & (1 << square) >= 1 {
-                    print!("\x1b[1m1\x1b[0m ");
-                } else {
-                    print!("\x1b[38;5;8m0\x1b[0m ");
-                }
-            }
-            println!();
-        }
-        println!();
-        println!("\x1b[34m   a b c d e f g h\x1b[0m");
-        println!();
+        println!("{}", self);
     }
 
     // rotate 180
@@ -301,7 +249,7 @@ impl BitBoard {
         self.0 = ((self.0 >> 4) & h4.0) | ((self.0 & h4.0) << 4);
         self.0 = ((self.0 >> 8) & v1.0) | ((self.0 & v1.0) << 8);
         self.0 = ((self.0 >> 16) & v2.0) | ((self.0 & v2.0) << 16);
-        self.0 = (self.0 >> 32) | (self.0 << 32);
+        self.0 = self.0.rotate_left(32);
     }
 
     // flip methods
@@ -310,7 +258,7 @@ impl BitBoard {
         let k2: BitBoard = BitBoard(0x0000FFFF0000FFFF);
         self.0 = ((self.0 >> 8) & k1.0) | ((self.0 & k1.0) << 8);
         self.0 = ((self.0 >> 16) & k2.0) | ((self.0 & k2.0) << 16);
-        self.0 = (self.0 >> 32) | (self.0 << 32);
+        self.0 = self.0.rotate_left(32);
     }
     pub fn flip_h(&mut self) {
         let k1: BitBoard = BitBoard(0x5555555555555555);
@@ -324,13 +272,139 @@ impl BitBoard {
     pub fn count_bits(&self) -> u32 {
         self.0.count_ones()
     }
-    // get least significant bit index
-    pub fn get_ls1b(&self) -> u32 {
-        if self.0 != 0 {
-            self.0.trailing_zeros()
-        } else {
-            0
+    // square of the least significant bit (closest to a8)
+    pub fn lsb(&self) -> Option<Square> {
+        Square::from_index(self.0.trailing_zeros() as usize)
+    }
+    // square of the most significant bit (closest to h1)
+    pub fn msb(&self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::ALL[63 - self.0.leading_zeros() as usize])
+    }
+    // remove the least significant bit and return its square
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let sq = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+}
+
+// the board as a grid of 1s and 0s, rank 8 at the top
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in Rank::ALL.into_iter().rev() {
+            write!(f, "\x1b[34m{}\x1b[0m  ", rank.to_char())?;
+            for file in File::ALL {
+                if self.get_bit(Square::new(file, rank)) {
+                    write!(f, "\x1b[1m1\x1b[0m ")?;
+                } else {
+                    write!(f, "\x1b[38;5;8m0\x1b[0m ")?;
+                }
+            }
+            writeln!(f)?;
         }
+        writeln!(f)?;
+        write!(f, "\x1b[34m   a b c d e f g h\x1b[0m")
+    }
+}
+
+// set squares from a8 to h1, popping the least significant bit each time
+#[derive(Clone, Copy, Debug)]
+pub struct Squares(BitBoard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        self.0.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_bits() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl IntoIterator for BitBoard {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        Squares(self)
+    }
+}
+
+impl FromIterator<Square> for BitBoard {
+    fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> BitBoard {
+        squares.into_iter().fold(BitBoard::empty(), |bb, sq| bb | BitBoard::from_sq(sq))
+    }
+}
+
+impl BitOr for BitBoard {
+    type Output = BitBoard;
+
+    fn bitor(self, rhs: BitBoard) -> BitBoard {
+        BitBoard(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for BitBoard {
+    type Output = BitBoard;
+
+    fn bitand(self, rhs: BitBoard) -> BitBoard {
+        BitBoard(self.0 & rhs.0)
+    }
+}
+
+impl BitXor for BitBoard {
+    type Output = BitBoard;
+
+    fn bitxor(self, rhs: BitBoard) -> BitBoard {
+        BitBoard(self.0 ^ rhs.0)
+    }
+}
+
+impl BitOrAssign for BitBoard {
+    fn bitor_assign(&mut self, rhs: BitBoard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAndAssign for BitBoard {
+    fn bitand_assign(&mut self, rhs: BitBoard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitXorAssign for BitBoard {
+    fn bitxor_assign(&mut self, rhs: BitBoard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl Not for BitBoard {
+    type Output = BitBoard;
+
+    fn not(self) -> BitBoard {
+        BitBoard(!self.0)
+    }
+}
+
+// shifts towards h1 (<<) and a8 (>>), bits shifted off the board are lost
+impl Shl<u32> for BitBoard {
+    type Output = BitBoard;
+
+    fn shl(self, rhs: u32) -> BitBoard {
+        BitBoard(self.0 << rhs)
+    }
+}
+
+impl Shr<u32> for BitBoard {
+    type Output = BitBoard;
+
+    fn shr(self, rhs: u32) -> BitBoard {
+        BitBoard(self.0 >> rhs)
     }
 }
 
@@ -343,11 +417,11 @@ mod tests {
         p.print();
         assert_eq!(p.0, 43980465111040);
 
-        let n = knight(Square::E4);
+        let n = knight::lookup(Square::E4);
         n.print();
         assert_eq!(n.0, 11333767002587136);
 
-        let k = king(Square::E4);
+        let k = king::lookup(Square::E4);
         k.print();
         assert_eq!(k.0, 61745389371392);
 
@@ -363,4 +437,31 @@ mod tests {
 
         assert_eq!(r.count_bits(), 11);
     }
+
+    #[test]
+    fn check_bb_ops() {
+        let a = BitBoard::from_sq(Square::A8) | BitBoard::from_sq(Square::E4);
+        let b: BitBoard = [Square::E4, Square::H1].into_iter().collect();
+
+        assert_eq!(a & b, BitBoard::from_sq(Square::E4));
+        assert_eq!((a ^ b).into_iter().collect::<Vec<_>>(), [Square::A8, Square::H1]);
+        assert_eq!(!(a | b) & a, BitBoard::empty());
+        assert_eq!(BitBoard::from_sq(Square::A8) << 9, BitBoard::from_sq(Square::B7));
+        assert_eq!(BitBoard::from_sq(Square::H1) >> 63, BitBoard::from_sq(Square::A8));
+
+        assert_eq!(b.lsb(), Some(Square::E4));
+        assert_eq!(b.msb(), Some(Square::H1));
+        assert_eq!(BitBoard::empty().lsb(), None);
+        assert_eq!(b.into_iter().len(), 2);
+
+        let mut c = a | b;
+        c.pop_bit(Square::E4);
+        c.pop_bit(Square::E4);
+        assert_eq!(c.pop_lsb(), Some(Square::A8));
+        assert_eq!(c, BitBoard::from_sq(Square::H1));
+
+        let grid = a.to_string();
+        assert_eq!(grid.lines().count(), 10);
+        assert!(grid.lines().next().unwrap().contains("\x1b[1m1"));
+    }
 }
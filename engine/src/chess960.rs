@@ -0,0 +1,152 @@
+use crate::position::Position;
+use crate::Kind;
+
+// number of Chess960 start positions
+pub const POSITIONS: u16 = 960;
+// Scharnagl number of the classical start position
+pub const CLASSICAL: u16 = 518;
+
+// the ten ways to put two knights on the five squares left after the bishops and the queen
+const KNIGHTS: [(usize, usize); 10] =
+    [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+// pieces from the a to the h file for a Scharnagl number (0-959)
+pub fn back_rank(number: u16) -> Option<[Kind; 8]> {
+    if number >= POSITIONS {
+        return None;
+    }
+
+    let mut rank = [None; 8];
+    let mut n = number as usize;
+
+    // light squared bishop on b, d, f or h, dark squared bishop on a, c, e or g
+    rank[n % 4 * 2 + 1] = Some(Kind::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(Kind::Bishop);
+    n /= 4;
+
+    // the queen and the knights go on the n-th empty square, the second knight first so that
+    // the first one still counts the same squares
+    place(&mut rank, n % 6, Kind::Queen);
+    let (first, second) = KNIGHTS[n / 6];
+    place(&mut rank, second, Kind::Knight);
+    place(&mut rank, first, Kind::Knight);
+
+    // rook, king and rook on the three squares left
+    for kind in [Kind::Rook, Kind::King, Kind::Rook] {
+        place(&mut rank, 0, kind);
+    }
+
+    Some(rank.map(|kind| kind.expect("every file is filled")))
+}
+
+fn place(rank: &mut [Option<Kind>; 8], n: usize, kind: Kind) {
+    let file = (0..8).filter(|file| rank[*file].is_none()).nth(n).expect("enough empty files");
+    rank[file] = Some(kind);
+}
+
+// Scharnagl number of a back rank, None if it is not a Chess960 setup (bishops on opposite
+// colours and the king between the rooks)
+pub fn scharnagl_number(rank: &[Kind; 8]) -> Option<u16> {
+    let files = |kind: Kind| -> Vec<usize> { (0..8).filter(|file| rank[*file] == kind).collect() };
+    // position of a file among the files not taken by the given kinds
+    let index = |file: usize, taken: &[Kind]| {
+        (0..file).filter(|other| !taken.contains(&rank[*other])).count()
+    };
+
+    let bishops = files(Kind::Bishop);
+    let knights = files(Kind::Knight);
+    let queens = files(Kind::Queen);
+    let rooks = files(Kind::Rook);
+    let kings = files(Kind::King);
+
+    if bishops.len() != 2 || knights.len() != 2 || queens.len() != 1 || rooks.len() != 2 {
+        return None;
+    }
+    if kings.len() != 1 || bishops[0] % 2 == bishops[1] % 2 {
+        return None;
+    }
+    if !(rooks[0] < kings[0] && kings[0] < rooks[1]) {
+        return None;
+    }
+
+    let (dark, light) =
+        if bishops[0] % 2 == 0 { (bishops[0], bishops[1]) } else { (bishops[1], bishops[0]) };
+    let queen = index(queens[0], &[Kind::Bishop]);
+    let pair = (
+        index(knights[0], &[Kind::Bishop, Kind::Queen]),
+        index(knights[1], &[Kind::Bishop, Kind::Queen]),
+    );
+    let knights = KNIGHTS.iter().position(|knights| *knights == pair)?;
+
+    Some((((knights * 6 + queen) * 4 + dark / 2) * 4 + light / 2) as u16)
+}
+
+// parse a back rank written with piece letters from the a to the h file, eg. RNBQKBNR
+pub fn parse_back_rank(text: &str) -> Option<[Kind; 8]> {
+    let kinds: Vec<Kind> = text.chars().map(Kind::from_char).collect::<Option<_>>()?;
+    kinds.try_into().ok()
+}
+
+// start position with the given setups for white and black, the same number for both sides is
+// normal Chess960 and different numbers Double Fischer Random
+pub fn start_position(white: u16, black: u16) -> Option<Position> {
+    let rank = |number: u16| -> Option<(String, String)> {
+        let kinds = back_rank(number)?;
+        let pieces = kinds.iter().map(|kind| kind.to_char()).collect();
+        let rooks = kinds.iter().enumerate().filter(|(_, kind)| **kind == Kind::Rook);
+        // Shredder-FEN castling rights, kingside rook first
+        let castling = rooks.rev().map(|(file, _)| (b'a' + file as u8) as char).collect();
+        Some((pieces, castling))
+    };
+
+    let (white, white_castling) = rank(white)?;
+    let (black, black_castling) = rank(black)?;
+
+    let fen = format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+        black,
+        white.to_ascii_uppercase(),
+        white_castling.to_ascii_uppercase(),
+        black_castling
+    );
+    let mut position = Position::from_fen(&fen).expect("generated FEN is valid");
+    position.chess960 = true;
+    Some(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess960::*;
+    use crate::fen;
+
+    #[test]
+    fn check_scharnagl_numbers() {
+        assert_eq!(back_rank(CLASSICAL), parse_back_rank("RNBQKBNR"));
+        assert_eq!(back_rank(0), parse_back_rank("BBQNNRKR"));
+        assert_eq!(back_rank(959), parse_back_rank("RKRNNQBB"));
+        assert_eq!(back_rank(POSITIONS), None);
+
+        for number in 0..POSITIONS {
+            let rank = back_rank(number).unwrap();
+            assert_eq!(scharnagl_number(&rank), Some(number));
+            assert!(start_position(number, number).unwrap().validate().is_empty());
+        }
+
+        // bishops on the same colour, king outside the rooks
+        assert_eq!(scharnagl_number(&parse_back_rank("BNBQKRNR").unwrap()), None);
+        assert_eq!(scharnagl_number(&parse_back_rank("KRBQNBNR").unwrap()), None);
+        assert_eq!(parse_back_rank("RNBQKBN"), None);
+    }
+
+    #[test]
+    fn check_start_positions() {
+        let position = start_position(CLASSICAL, CLASSICAL).unwrap();
+        assert_eq!(position.to_fen(), fen::START_POS.trim());
+        assert!(position.chess960);
+
+        let position = start_position(0, CLASSICAL).unwrap();
+        assert_eq!(position.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(position.castling_id, [Some(7), Some(5), Some(7), Some(0)]);
+    }
+}
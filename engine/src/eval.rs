@@ -0,0 +1,112 @@
+use crate::position::Position;
+use crate::{Colour, Kind};
+
+// piece values in centipawns, indexed by Kind
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+// piece-square tables from white's point of view, laid out like Square (a8 first)
+// https://www.chessprogramming.org/Simplified_Evaluation_Function
+#[rustfmt::skip]
+const PST: [[i32; 64]; 6] = [
+    [
+         0,  0,  0,  0,  0,  0,  0,  0,
+        50, 50, 50, 50, 50, 50, 50, 50,
+        10, 10, 20, 30, 30, 20, 10, 10,
+         5,  5, 10, 25, 25, 10,  5,  5,
+         0,  0,  0, 20, 20,  0,  0,  0,
+         5, -5,-10,  0,  0,-10, -5,  5,
+         5, 10, 10,-20,-20, 10, 10,  5,
+         0,  0,  0,  0,  0,  0,  0,  0,
+    ],
+    [
+        -50,-40,-30,-30,-30,-30,-40,-50,
+        -40,-20,  0,  0,  0,  0,-20,-40,
+        -30,  0, 10, 15, 15, 10,  0,-30,
+        -30,  5, 15, 20, 20, 15,  5,-30,
+        -30,  0, 15, 20, 20, 15,  0,-30,
+        -30,  5, 10, 15, 15, 10,  5,-30,
+        -40,-20,  0,  5,  5,  0,-20,-40,
+        -50,-40,-30,-30,-30,-30,-40,-50,
+    ],
+    [
+        -20,-10,-10,-10,-10,-10,-10,-20,
+        -10,  0,  0,  0,  0,  0,  0,-10,
+        -10,  0,  5, 10, 10,  5,  0,-10,
+        -10,  5,  5, 10, 10,  5,  5,-10,
+        -10,  0, 10, 10, 10, 10,  0,-10,
+        -10, 10, 10, 10, 10, 10, 10,-10,
+        -10,  5,  0,  0,  0,  0,  5,-10,
+        -20,-10,-10,-10,-10,-10,-10,-20,
+    ],
+    [
+          0,  0,  0,  0,  0,  0,  0,  0,
+          5, 10, 10, 10, 10, 10, 10,  5,
+         -5,  0,  0,  0,  0,  0,  0, -5,
+         -5,  0,  0,  0,  0,  0,  0, -5,
+         -5,  0,  0,  0,  0,  0,  0, -5,
+         -5,  0,  0,  0,  0,  0,  0, -5,
+         -5,  0,  0,  0,  0,  0,  0, -5,
+          0,  0,  0,  5,  5,  0,  0,  0,
+    ],
+    [
+        -20,-10,-10, -5, -5,-10,-10,-20,
+        -10,  0,  0,  0,  0,  0,  0,-10,
+        -10,  0,  5,  5,  5,  5,  0,-10,
+         -5,  0,  5,  5,  5,  5,  0, -5,
+          0,  0,  5,  5,  5,  5,  0, -5,
+        -10,  5,  5,  5,  5,  5,  0,-10,
+        -10,  0,  5,  0,  0,  0,  0,-10,
+        -20,-10,-10, -5, -5,-10,-10,-20,
+    ],
+    [
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -20,-30,-30,-40,-40,-30,-30,-20,
+        -10,-20,-20,-20,-20,-20,-20,-10,
+         20, 20,  0,  0,  0,  0, 20, 20,
+         20, 30, 10,  0,  0, 10, 30, 20,
+    ],
+];
+
+// value of a piece on a square, black uses the tables mirrored vertically
+pub fn piece_square(kind: Kind, colour: Colour, sq: usize) -> i32 {
+    let sq = if colour == Colour::White { sq } else { sq ^ 56 };
+    PIECE_VALUES[kind as usize] + PST[kind as usize][sq]
+}
+
+// static evaluation from the side to move's point of view
+pub fn evaluate(position: &Position) -> i32 {
+    let mut score = 0;
+
+    for (sq, piece) in position.pieces.iter().enumerate() {
+        if let Some(piece) = piece {
+            let value = piece_square(piece.kind, piece.colour, sq);
+            score += if piece.colour == Colour::White { value } else { -value };
+        }
+    }
+
+    match position.side_to_move {
+        Colour::White => score,
+        _ => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::*;
+    use crate::fen;
+
+    #[test]
+    fn check_symmetric_eval() {
+        let position = Position::from_fen(fen::START_POS).unwrap();
+        assert_eq!(evaluate(&position), 0);
+
+        // the same position with colours swapped scores the same for the side to move
+        let white = Position::from_fen("4k3/8/8/8/8/2N5/PP6/4K3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/pp6/2n5/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(evaluate(&white), evaluate(&black));
+        assert!(evaluate(&white) > 300);
+    }
+}
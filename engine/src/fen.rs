@@ -1,6 +1,6 @@
-use crate::utils::match_u32_to_sq;
-use crate::{Colour, GameStatus, Piece, Square};
-use std::process::exit;
+use crate::{Colour, File, GameStatus, Kind, Piece, Rank, Square};
+use std::error::Error;
+use std::fmt;
 
 pub const EMPTY_BOARD: &str = "8/8/8/8/8/8/8/8 w - - 0 0";
 pub const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ";
@@ -16,218 +16,577 @@ macro_rules! fen_log {
     };
 }
 
+// part of a FEN string an error was found in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfMoveClock,
+    FullMoveCount,
+    // EPD operations after the four position fields
+    Operations,
+}
+
+// what went wrong while parsing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenErrorKind {
+    // the field is not there at all
+    Missing,
+    // a character that is not allowed in the field
+    InvalidCharacter(char),
+    // placement does not have exactly 8 ranks
+    RankCount(usize),
+    // a rank (counted from the 8th) does not describe exactly 8 squares
+    RankLength { rank: usize, squares: usize },
+    // not a number that fits the clock
+    InvalidNumber(String),
+    // EPD string operand without its closing quote
+    UnterminatedString,
+}
+
+// error while parsing a FEN string, with the character offset (0 based) of the problem
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FenError {
+    pub field: FenField,
+    pub offset: usize,
+    pub kind: FenErrorKind,
+}
+
+impl FenError {
+    fn new(field: FenField, offset: usize, kind: FenErrorKind) -> FenError {
+        FenError { field, offset, kind }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let field = match self.field {
+            FenField::Placement => "piece placement",
+            FenField::SideToMove => "active colour",
+            FenField::Castling => "castling ability",
+            FenField::EnPassant => "en passant square",
+            FenField::HalfMoveClock => "half move clock",
+            FenField::FullMoveCount => "full move count",
+            FenField::Operations => "EPD operations",
+        };
+
+        match &self.kind {
+            FenErrorKind::Missing => write!(f, "missing {} (at {})", field, self.offset),
+            FenErrorKind::InvalidCharacter(c) => {
+                write!(f, "invalid character '{}' in {} (at {})", c, field, self.offset)
+            }
+            FenErrorKind::RankCount(count) => {
+                write!(f, "expected 8 ranks in {}, found {} (at {})", field, count, self.offset)
+            }
+            FenErrorKind::RankLength { rank, squares } => write!(
+                f,
+                "rank {} describes {} squares instead of 8 (at {})",
+                8 - rank,
+                squares,
+                self.offset
+            ),
+            FenErrorKind::InvalidNumber(input) => {
+                write!(f, "invalid {} '{}' (at {})", field, input, self.offset)
+            }
+            FenErrorKind::UnterminatedString => {
+                write!(f, "string is never closed in {} (at {})", field, self.offset)
+            }
+        }
+    }
+}
+
+impl Error for FenError {}
+
 pub fn default() -> GameStatus {
-    return_state("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+    return_state(START_POS).expect("start position is valid")
 }
 
-pub fn return_state(fen: &str) -> GameStatus {
+pub fn return_state(fen: &str) -> Result<GameStatus, FenError> {
     parse_fen(GameStatus::default_gamestatus(), fen)
 }
 
-fn parse_fen(def: GameStatus, input: &str) -> GameStatus {
-    let mut fen = input.split_whitespace();
+// split on whitespace, keeping the character offset of every field
+fn fields(input: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+
+    for (offset, (i, c)) in input.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((offset, i)),
+            (true, Some((field_offset, field_start))) => {
+                fields.push((field_offset, &input[field_start..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((field_offset, field_start)) = start {
+        fields.push((field_offset, &input[field_start..]));
+    }
+    fields
+}
+
+fn parse_fen(def: GameStatus, input: &str) -> Result<GameStatus, FenError> {
+    let fields = fields(input);
+    let end = input.chars().count();
     let mut state: GameStatus = def;
 
-    let pieces: [Option<Piece>; 64] = pieces(fen.next().unwrap());
+    let field = |i: usize, name: FenField| -> Result<(usize, &str), FenError> {
+        fields.get(i).copied().ok_or_else(|| FenError::new(name, end, FenErrorKind::Missing))
+    };
+
+    state.pieces = pieces(field(0, FenField::Placement)?)?;
+    state.side_to_move = active_side(field(1, FenField::SideToMove)?)?;
+    state.castling_id = castling_ability(field(2, FenField::Castling)?, &state.pieces)?;
+    state.chess960 = needs_chess960(&state.pieces, state.castling_id);
+    state.en_passant = en_passant(field(3, FenField::EnPassant)?)?;
 
-    let colour: Colour = active_side(fen.next().unwrap());
+    // the clocks are often left out, fall back to the values of a fresh game
+    state.half_move_clock = match fields.get(4) {
+        Some(field) => clock(*field, FenField::HalfMoveClock)?,
+        None => 0,
+    };
+    state.full_move_count = match fields.get(5) {
+        Some(field) => clock(*field, FenField::FullMoveCount)?,
+        None => 1,
+    };
+
+    Ok(state)
+}
 
-    if colour == Colour::Undefined {
-        fen_log!("Invalid FEN string: Failed to parse active colour.");
-        exit(1);
+fn active_side((offset, input): (usize, &str)) -> Result<Colour, FenError> {
+    match input {
+        "w" => Ok(Colour::White),
+        "b" => Ok(Colour::Black),
+        _ => {
+            // point at the first character that makes the field invalid
+            let (i, c) = match input.chars().next() {
+                Some('w') | Some('b') => (1, input.chars().nth(1).unwrap_or(' ')),
+                Some(c) => (0, c),
+                None => (0, ' '),
+            };
+            Err(FenError::new(FenField::SideToMove, offset + i, FenErrorKind::InvalidCharacter(c)))
+        }
     }
+}
 
-    let castling_id: [bool; 4] = castling_ability(fen.next().unwrap());
+// row index of the rank the pieces of a colour start on
+fn back_rank(colour: Colour) -> usize {
+    match colour {
+        Colour::White => 7,
+        _ => 0,
+    }
+}
 
-    let en_passant = en_passant(fen.next().unwrap());
-    let half_move_clock: u16 = halfmove_clock(fen.next().unwrap());
-    let full_move_count: u16 = fullmove_count(fen.next().unwrap());
+fn is_piece(pieces: &[Option<Piece>; 64], sq: usize, kind: Kind, colour: Colour) -> bool {
+    pieces[sq] == Some(Piece::new(kind, colour))
+}
 
-    state.pieces = pieces;
-    state.side_to_move = colour;
-    state.castling_id = castling_id;
-    state.en_passant = en_passant;
-    state.half_move_clock = half_move_clock;
-    state.full_move_count = full_move_count;
+// file of the king if it is still on its back rank
+fn king_file(pieces: &[Option<Piece>; 64], colour: Colour) -> Option<usize> {
+    let rank = back_rank(colour);
+    (0..8).find(|file| is_piece(pieces, rank * 8 + file, Kind::King, colour))
+}
 
-    state
+// files of the rooks of a colour on its back rank
+fn rook_files(pieces: &[Option<Piece>; 64], colour: Colour) -> impl Iterator<Item = usize> + '_ {
+    let rank = back_rank(colour);
+    (0..8).filter(move |file| is_piece(pieces, rank * 8 + file, Kind::Rook, colour))
 }
 
-fn active_side(input: &str) -> Colour {
-    match input {
-        "w" => Colour::White,
-        "b" => Colour::Black,
-        _ => Colour::Undefined,
+// castling rights in X-FEN (KQkq meaning the outermost rook on that side of the king) or
+// Shredder-FEN (the file of the rook, eg. HAha), both may be mixed
+fn castling_ability(
+    (offset, input): (usize, &str),
+    pieces: &[Option<Piece>; 64],
+) -> Result<[Option<usize>; 4], FenError> {
+    let mut castling_id = [None; 4];
+
+    if input == "-" {
+        return Ok(castling_id);
     }
+
+    for (i, c) in input.chars().enumerate() {
+        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+        let base = if colour == Colour::White { 0 } else { 2 };
+        // without a king on the back rank assume the standard setup, validation reports it
+        let king = king_file(pieces, colour).unwrap_or(4);
+
+        let (right, file) = match c.to_ascii_lowercase() {
+            'k' => (0, rook_files(pieces, colour).filter(|file| *file > king).last().unwrap_or(7)),
+            'q' => (1, rook_files(pieces, colour).find(|file| *file < king).unwrap_or(0)),
+            f @ 'a'..='h' if f as usize - 'a' as usize != king => {
+                let file = f as usize - 'a' as usize;
+                (if file > king { 0 } else { 1 }, file)
+            }
+            _ => {
+                let kind = FenErrorKind::InvalidCharacter(c);
+                return Err(FenError::new(FenField::Castling, offset + i, kind));
+            }
+        };
+        castling_id[base + right] = Some(file);
+    }
+    Ok(castling_id)
+}
+
+// castling rights with the king off the e file or a rook off the a and h files can only be
+// played with Chess960 rules
+fn needs_chess960(pieces: &[Option<Piece>; 64], castling_id: [Option<usize>; 4]) -> bool {
+    castling_id.iter().enumerate().any(|(i, file)| {
+        let colour = if i < 2 { Colour::White } else { Colour::Black };
+        let standard = if i % 2 == 0 { 7 } else { 0 };
+
+        file.is_some_and(|file| {
+            file != standard || king_file(pieces, colour).is_some_and(|king| king != 4)
+        })
+    })
 }
 
-fn castling_ability(input: &str) -> [bool; 4] {
+fn en_passant((offset, input): (usize, &str)) -> Result<Option<Vec<Square>>, FenError> {
     if input == "-" {
-        [false, false, false, false]
-    } else {
-        let mut castling_id = [false; 4];
-        let mut castling_id_str = input.to_string();
-        castling_id_str.retain(|c| c != '-');
-        for c in castling_id_str.chars() {
-            match c {
-                'K' => castling_id[0] = true,
-                'Q' => castling_id[1] = true,
-                'k' => castling_id[2] = true,
-                'q' => castling_id[3] = true,
-                _ => {
-                    fen_log!("Invalid FEN string: Failed to parse castling ability.");
-                    exit(1);
-                }
+        return Ok(None);
+    }
+
+    let chars: Vec<char> = input.chars().collect();
+    let mut ep_vec = Vec::new();
+
+    for (i, pair) in chars.chunks(2).enumerate() {
+        let invalid = |j: usize, c: char| {
+            let kind = FenErrorKind::InvalidCharacter(c);
+            FenError::new(FenField::EnPassant, offset + i * 2 + j, kind)
+        };
+
+        let file = File::from_char(pair[0]).ok_or_else(|| invalid(0, pair[0]))?;
+        let rank = match pair.get(1) {
+            Some(c) => Rank::from_char(*c).ok_or_else(|| invalid(1, *c))?,
+            None => {
+                return Err(FenError::new(
+                    FenField::EnPassant,
+                    offset + i * 2 + 1,
+                    FenErrorKind::Missing,
+                ))
             }
-        }
-        castling_id
+        };
+
+        ep_vec.push(Square::new(file, rank));
     }
+    Ok(Some(ep_vec))
 }
 
-// rather atrocious, but it works
-fn en_passant(input: &str) -> Option<Vec<Square>> {
-    if input.chars().all(char::is_whitespace) | input.contains('-') {
-        None
-    } else {
-        let chars = input.chars().collect::<Vec<char>>();
+fn clock((offset, input): (usize, &str), field: FenField) -> Result<u16, FenError> {
+    input
+        .parse::<u16>()
+        .map_err(|_| FenError::new(field, offset, FenErrorKind::InvalidNumber(input.to_string())))
+}
 
-        if chars.len() % 2 == 0 {
-            let mut ep_vec = Vec::new();
-            let mut sq;
+// https://github.com/ucarion/fen/blob/master/src/lib.rs#L139-L173
 
-            for i in 0..chars.len() {
-                if i % 2 == 0 {
-                    let valid_chars = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-                    let mut invalid = true;
+fn pieces((offset, input): (usize, &str)) -> Result<[Option<Piece>; 64], FenError> {
+    let mut placement = [None; 64];
+    let lines: Vec<&str> = input.split('/').collect();
 
-                    for c in valid_chars.iter() {
-                        if chars[i] == *c {
-                            invalid = false;
-                        }
-                    }
+    if lines.len() != 8 {
+        let kind = FenErrorKind::RankCount(lines.len());
+        return Err(FenError::new(FenField::Placement, offset, kind));
+    }
 
-                    if i < chars.len() - 1 {
-                        if !chars[i].is_alphabetic() | !chars[i + 1].is_numeric() {
-                            fen_log!("Invalid FEN string: Failed to parse en passant square.");
-                            exit(1);
-                        }
+    let mut char_offset = offset;
+    for (rank, pieces) in lines.iter().enumerate() {
+        let mut file: usize = 0;
 
-                        if chars[i + 1]
-                            .to_digit(10)
-                            .expect("Could not parse character to digit (en passant)")
-                            > 8
-                        {
-                            invalid = true;
+        for piece_char in pieces.chars() {
+            match piece_char.to_digit(10) {
+                Some(n @ 1..=8) => file += n as usize,
+                _ => match Piece::from_char(piece_char) {
+                    Some(piece) => {
+                        if file < 8 {
+                            placement[rank * 8 + file] = Some(piece);
                         }
+                        file += 1;
                     }
 
-                    if invalid {
-                        fen_log!("Invalid FEN string: Failed to parse en passant square.");
-                        exit(1);
+                    None => {
+                        let kind = FenErrorKind::InvalidCharacter(piece_char);
+                        return Err(FenError::new(FenField::Placement, char_offset, kind));
                     }
+                },
+            }
 
-                    let file = match chars[i] {
-                        'a' => 0,
-                        'b' => 1,
-                        'c' => 2,
-                        'd' => 3,
-                        'e' => 4,
-                        'f' => 5,
-                        'g' => 6,
-                        'h' => 7,
-                        _ => {
-                            fen_log!("Invalid FEN string: Failed to parse en passant square.");
-                            exit(1);
-                        }
-                    };
-
-                    let rank = match chars[i + 1] {
-                        '1' => 7,
-                        '2' => 6,
-                        '3' => 5,
-                        '4' => 4,
-                        '5' => 3,
-                        '6' => 2,
-                        '7' => 1,
-                        '8' => 0,
-                        _ => {
-                            fen_log!("Invalid FEN string: Failed to parse en passant square.");
-                            exit(1);
-                        }
-                    };
+            if file > 8 {
+                let kind = FenErrorKind::RankLength { rank, squares: file };
+                return Err(FenError::new(FenField::Placement, char_offset, kind));
+            }
+            char_offset += 1;
+        }
+
+        if file != 8 {
+            let kind = FenErrorKind::RankLength { rank, squares: file };
+            return Err(FenError::new(FenField::Placement, char_offset, kind));
+        }
 
-                    sq = rank * 8 + file;
+        // skip the '/'
+        char_offset += 1;
+    }
+    Ok(placement)
+}
 
-                    ep_vec.push(match_u32_to_sq(sq as u32));
+// write the position as a FEN string, only the first en passant square is kept. Castling rights
+// are written as X-FEN, which is plain FEN for standard chess
+pub fn to_fen(state: &GameStatus) -> String {
+    write_fen(state, false)
+}
+
+// FEN with castling rights as rook files (Shredder-FEN), eg. HAha for the start position
+pub fn to_shredder_fen(state: &GameStatus) -> String {
+    write_fen(state, true)
+}
+
+fn write_fen(state: &GameStatus, shredder: bool) -> String {
+    let mut fen = String::new();
+
+    for rank in 0..8 {
+        let mut empty = 0;
+
+        for file in 0..8 {
+            match state.pieces[rank * 8 + file] {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(piece.symbol);
                 }
+                None => empty += 1,
             }
+        }
 
-            Some(ep_vec)
-        } else {
-            fen_log!("Invalid FEN string: Failed to parse en passant square.");
-            exit(1);
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if rank < 7 {
+            fen.push('/');
         }
     }
-}
 
-fn halfmove_clock(input: &str) -> u16 {
-    let mut halfmove_clock = String::new();
-    for c in input.chars() {
-        if c.is_ascii_digit() {
-            halfmove_clock.push(c);
-        }
+    fen.push_str(match state.side_to_move {
+        Colour::Black => " b ",
+        _ => " w ",
+    });
+
+    let mut castling = String::new();
+    for (i, file) in state.castling_id.iter().enumerate() {
+        let file = match file {
+            Some(file) => *file,
+            None => continue,
+        };
+        let colour = if i < 2 { Colour::White } else { Colour::Black };
+
+        // X-FEN only needs the file when another rook stands further out on the same side
+        let outermost = match i % 2 {
+            0 => rook_files(&state.pieces, colour).all(|rook| rook <= file),
+            _ => rook_files(&state.pieces, colour).all(|rook| rook >= file),
+        };
+        let c = match (shredder || !outermost, i % 2) {
+            (true, _) => (b'a' + file as u8) as char,
+            (false, 0) => 'k',
+            (false, _) => 'q',
+        };
+        castling.push(if colour == Colour::White { c.to_ascii_uppercase() } else { c });
     }
-    halfmove_clock.parse::<u16>().unwrap()
+    fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+    match state.en_passant.as_ref().and_then(|squares| squares.first()) {
+        Some(sq) => fen.push_str(&format!(" {} ", sq)),
+        None => fen.push_str(" - "),
+    }
+
+    fen.push_str(&format!("{} {}", state.half_move_clock, state.full_move_count));
+    fen
 }
 
-fn fullmove_count(input: &str) -> u16 {
-    let mut fullmove_clock = String::new();
-    for c in input.chars() {
-        if c.is_ascii_digit() {
-            fullmove_clock.push(c);
-        }
+// EPD record: the first four FEN fields followed by operations, eg.
+// r1b1k2r/ppp2ppp/8/4p3/8/8/PPP2PPP/R3KB1R w KQkq - bm Bb5+; id "test 1";
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epd {
+    // clocks come from the hmvc and fmvn opcodes, or are those of a fresh game
+    pub state: GameStatus,
+    // opcodes in the order they were written, with their operands (without quotes)
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        let operation = self.operations.iter().find(|(name, _)| name == opcode);
+        operation.map(|(_, operands)| operands.as_slice())
+    }
+
+    // first operand of an opcode, for the ones that take a single value
+    fn operand(&self, opcode: &str) -> Option<&str> {
+        self.operands(opcode).and_then(|operands| operands.first()).map(String::as_str)
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operand("id")
+    }
+
+    // best moves (bm) and moves to avoid (am) in SAN
+    pub fn best_moves(&self) -> &[String] {
+        self.operands("bm").unwrap_or_default()
+    }
+
+    pub fn avoid_moves(&self) -> &[String] {
+        self.operands("am").unwrap_or_default()
+    }
+
+    // comments c0 to c9
+    pub fn comment(&self, n: usize) -> Option<&str> {
+        self.operand(&format!("c{}", n))
+    }
+
+    // centipawn evaluation (ce) from the side to move's point of view
+    pub fn centipawns(&self) -> Option<i32> {
+        self.operand("ce").and_then(|ce| ce.parse().ok())
+    }
+
+    // analysis count depth (acd)
+    pub fn depth(&self) -> Option<u32> {
+        self.operand("acd").and_then(|acd| acd.parse().ok())
+    }
+
+    // predicted variation (pv) in SAN
+    pub fn pv(&self) -> &[String] {
+        self.operands("pv").unwrap_or_default()
     }
-    fullmove_clock.parse::<u16>().unwrap()
 }
 
-// https://github.com/ucarion/fen/blob/master/src/lib.rs#L139-L173
+// opcodes whose operands are numbers, checked while parsing
+const NUMERIC_OPCODES: [&str; 4] = ["ce", "acd", "hmvc", "fmvn"];
 
-fn pieces(input: &str) -> [Option<Piece>; 64] {
-    let mut placement = [None; 64];
-    let lines: Vec<&str> = input.split('/').collect();
+// opcodes whose operand is text, always written in quotes
+fn is_string_opcode(opcode: &str) -> bool {
+    opcode == "id"
+        || (opcode.len() == 2 && opcode.starts_with('c') && opcode.ends_with(char::is_numeric))
+}
 
-    if lines.len() != 8 {
-        fen_log!("Invalid FEN string: Failed to parse placement.");
-        exit(1);
+pub fn return_epd(input: &str) -> Result<Epd, FenError> {
+    // the operations start right after the en passant field
+    let fields = fields(input);
+    let split = match fields.get(3) {
+        Some((_, field)) => field.as_ptr() as usize - input.as_ptr() as usize + field.len(),
+        None => input.len(),
+    };
+    let mut state = parse_fen(GameStatus::default_gamestatus(), &input[..split])?;
+
+    let offset = input[..split].chars().count();
+    let operations = operations(offset, &input[split..])?;
+
+    let epd = Epd { state: state.clone(), operations };
+    if let Some(hmvc) = epd.operand("hmvc") {
+        state.half_move_clock = hmvc.parse().expect("checked while parsing");
     }
+    if let Some(fmvn) = epd.operand("fmvn") {
+        state.full_move_count = fmvn.parse().expect("checked while parsing");
+    }
+    Ok(Epd { state, ..epd })
+}
 
-    for (rank, pieces) in lines.iter().enumerate() {
-        let mut file: usize = 0;
+// opcode operand*; ... with operands separated by whitespace, strings in double quotes may
+// contain both. The ';' after the last operation may be left out
+fn operations(offset: usize, input: &str) -> Result<Vec<(String, Vec<String>)>, FenError> {
+    let error =
+        |i: usize, kind: FenErrorKind| FenError::new(FenField::Operations, offset + i, kind);
+    let mut operations: Vec<(String, Vec<String>)> = Vec::new();
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut chars = input.chars().enumerate().peekable();
+
+    let mut finish = |words: &mut Vec<(usize, String)>| -> Result<(), FenError> {
+        if words.is_empty() {
+            return Ok(());
+        }
+        let (start, opcode) = words.remove(0);
+        // a letter followed by letters, digits and underscores
+        let invalid = opcode.chars().enumerate().find(|(i, c)| {
+            !(c.is_ascii_alphabetic() || (*i > 0 && (c.is_ascii_digit() || *c == '_')))
+        });
+        if let Some((i, c)) = invalid {
+            return Err(error(start + i, FenErrorKind::InvalidCharacter(c)));
+        }
 
-        for piece_char in pieces.chars() {
-            match piece_char.to_digit(10) {
-                Some(n) => {
-                    file += n as usize;
-                    if file > 8 {
-                        fen_log!("Invalid FEN string: Failed to parse placement.");
-                        exit(1);
+        if NUMERIC_OPCODES.contains(&opcode.as_str()) {
+            let (i, number) = words
+                .first()
+                .cloned()
+                .ok_or_else(|| error(start + opcode.len(), FenErrorKind::Missing))?;
+            let valid = match opcode.as_str() {
+                "ce" => number.parse::<i32>().is_ok(),
+                "acd" => number.parse::<u32>().is_ok(),
+                _ => number.parse::<u16>().is_ok(),
+            };
+            if !valid {
+                return Err(error(i, FenErrorKind::InvalidNumber(number)));
+            }
+        }
+        operations.push((opcode, words.drain(..).map(|(_, word)| word).collect()));
+        Ok(())
+    };
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ';' => finish(&mut words)?,
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => word.push(c),
+                        None => return Err(error(i, FenErrorKind::UnterminatedString)),
                     }
                 }
-                None => match Piece::from_char(piece_char) {
-                    Some(piece) => {
-                        placement[rank * 8 + file] = Some(piece);
-                        file += 1;
-                    }
+                words.push((i, word));
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| !c.is_whitespace() && *c != ';') {
+                    word.push(c);
+                }
+                words.push((i, word));
+            }
+        }
+    }
+    finish(&mut words)?;
+    Ok(operations)
+}
 
-                    None => {
-                        fen_log!("Invalid FEN string: Failed to parse piece.");
-                        exit(1);
-                    }
-                },
+// write the record with the clocks as hmvc and fmvn when they are not those of a fresh game and
+// the record has no such opcodes
+pub fn to_epd(epd: &Epd) -> String {
+    let fen = to_fen(&epd.state);
+    let mut record = fen.rsplitn(3, ' ').nth(2).unwrap_or(&fen).to_string();
+
+    let mut operations = epd.operations.clone();
+    let clocks = [("hmvc", epd.state.half_move_clock, 0), ("fmvn", epd.state.full_move_count, 1)];
+    for (opcode, value, fresh) in clocks {
+        if value != fresh && epd.operands(opcode).is_none() {
+            operations.push((opcode.to_string(), vec![value.to_string()]));
+        }
+    }
+
+    for (opcode, operands) in &operations {
+        record.push(' ');
+        record.push_str(opcode);
+        for operand in operands {
+            if is_string_opcode(opcode) || operand.is_empty() || operand.contains([' ', ';']) {
+                record.push_str(&format!(" \"{}\"", operand));
+            } else {
+                record.push_str(&format!(" {}", operand));
             }
         }
+        record.push(';');
     }
-    placement
+    record
 }
 
 // display the board
@@ -235,10 +594,9 @@ pub fn print_board(game_state: &GameStatus) {
     let mut board: Vec<char> = Vec::new();
 
     for piece in game_state.pieces {
-        if piece == None {
-            board.push(' ');
-        } else {
-            board.push(piece.as_ref().unwrap().symbol);
+        match piece {
+            Some(piece) => board.push(piece.symbol),
+            None => board.push(' '),
         }
     }
 
@@ -266,28 +624,29 @@ pub fn print_side_to_move(game_state: &GameStatus) {
 }
 
 pub fn print_en_passant_squares(game_state: &GameStatus) {
-    if game_state.en_passant == None {
-        println!("No en passant squares.");
-    } else {
-        print!("Available en passant squares: ");
-        for i in game_state.en_passant.as_ref().unwrap() {
-            print!("{:?} ", i);
+    match &game_state.en_passant {
+        Some(squares) => {
+            print!("Available en passant squares: ");
+            for i in squares {
+                print!("{:?} ", i);
+            }
+            println!();
         }
-        println!();
+        None => println!("No en passant squares."),
     }
 }
 
 pub fn print_castling_ability(game_state: &GameStatus) {
-    if game_state.castling_id[0] {
+    if game_state.castling_id[0].is_some() {
         println!("White can castle kingside.");
     }
-    if game_state.castling_id[1] {
+    if game_state.castling_id[1].is_some() {
         println!("White can castle queenside.");
     }
-    if game_state.castling_id[2] {
+    if game_state.castling_id[2].is_some() {
         println!("Black can castle kingside.");
     }
-    if game_state.castling_id[3] {
+    if game_state.castling_id[3].is_some() {
         println!("Black can castle queenside.");
     }
 }
@@ -308,3 +667,97 @@ pub fn print_all(game_state: &GameStatus) {
     print_half_moves(game_state);
     print_full_moves(game_state);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::*;
+    use crate::position::Position;
+
+    fn error(fen: &str) -> FenError {
+        return_state(fen).unwrap_err()
+    }
+
+    #[test]
+    fn check_fen_errors() {
+        let err = error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1");
+        assert_eq!(
+            err,
+            FenError::new(FenField::Placement, 42, FenErrorKind::InvalidCharacter('X'))
+        );
+
+        let err = error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1");
+        assert_eq!(err.kind, FenErrorKind::RankCount(7));
+
+        let err = error("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(err.kind, FenErrorKind::RankLength { rank: 1, squares: 9 });
+        assert_eq!(err.offset, 17);
+
+        let err = error("8/8/8/8/8/8/8/7 w - - 0 1");
+        assert_eq!(err.kind, FenErrorKind::RankLength { rank: 7, squares: 7 });
+
+        let err = error("8/8/8/8/8/8/8/8 x - - 0 1");
+        assert_eq!(
+            err,
+            FenError::new(FenField::SideToMove, 16, FenErrorKind::InvalidCharacter('x'))
+        );
+
+        let err = error("8/8/8/8/8/8/8/8 w KQz - 0 1");
+        assert_eq!(err, FenError::new(FenField::Castling, 20, FenErrorKind::InvalidCharacter('z')));
+
+        let err = error("8/8/8/8/8/8/8/8 w - e9 0 1");
+        assert_eq!(
+            err,
+            FenError::new(FenField::EnPassant, 21, FenErrorKind::InvalidCharacter('9'))
+        );
+
+        let err = error("8/8/8/8/8/8/8/8 w - - x 1");
+        assert_eq!(err.field, FenField::HalfMoveClock);
+        assert_eq!(err.kind, FenErrorKind::InvalidNumber("x".to_string()));
+
+        assert_eq!(error("8/8/8/8/8/8/8/8 w").kind, FenErrorKind::Missing);
+        assert_eq!(error("").field, FenField::Placement);
+        assert!(Position::from_fen("not a fen").is_err());
+    }
+
+    #[test]
+    fn check_epd() {
+        let record = r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "mate; in 3"; ce +32767;acd 12 ;pv Qg6 fxg6 Nxg6#"#;
+        let epd = return_epd(record).unwrap();
+
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.best_moves(), ["Qg6"]);
+        assert!(epd.avoid_moves().is_empty());
+        assert_eq!(epd.comment(0), Some("mate; in 3"));
+        assert_eq!(epd.comment(1), None);
+        assert_eq!(epd.centipawns(), Some(32767));
+        assert_eq!(epd.depth(), Some(12));
+        assert_eq!(epd.pv(), ["Qg6", "fxg6", "Nxg6#"]);
+        assert_eq!(epd.state, return_state(record.split(" bm").next().unwrap()).unwrap());
+        assert_eq!(
+            to_epd(&epd),
+            r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "mate; in 3"; ce +32767; acd 12; pv Qg6 fxg6 Nxg6#;"#
+        );
+
+        let epd = return_epd("8/8/8/8/8/8/8/8 b - - am Kd7 Ke7; hmvc 7; fmvn 30").unwrap();
+        assert_eq!(epd.avoid_moves(), ["Kd7", "Ke7"]);
+        assert_eq!((epd.state.half_move_clock, epd.state.full_move_count), (7, 30));
+        assert_eq!(return_epd("8/8/8/8/8/8/8/8 b - -").unwrap().operations, []);
+
+        let err = return_epd("8/8/8/8/8/8/8/8 w - - id \"open").unwrap_err();
+        assert_eq!(err, FenError::new(FenField::Operations, 25, FenErrorKind::UnterminatedString));
+        let err = return_epd("8/8/8/8/8/8/8/8 w - - acd x;").unwrap_err();
+        assert_eq!(err.kind, FenErrorKind::InvalidNumber("x".to_string()));
+        let err = return_epd("8/8/8/8/8/8/8/8 w - - 1bm e4;").unwrap_err();
+        assert_eq!(err.kind, FenErrorKind::InvalidCharacter('1'));
+        assert_eq!(return_epd("8/8/8/8/8/8/8/8 w").unwrap_err().kind, FenErrorKind::Missing);
+    }
+
+    #[test]
+    fn check_missing_clocks() {
+        let state =
+            return_state("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3").unwrap();
+        assert_eq!(state.half_move_clock, 0);
+        assert_eq!(state.full_move_count, 1);
+        assert_eq!(state.en_passant, Some(vec![Square::E3]));
+    }
+}
@@ -0,0 +1,447 @@
+use crate::fen::FenError;
+use crate::movegen::{generate_legal_moves, Move};
+use crate::pgn::{GameResult, PgnGame, PgnNode};
+use crate::position::Position;
+use crate::Colour;
+use std::error::Error;
+use std::fmt;
+
+// why a game is drawn, the repetition and move rules that only allow a player to claim the
+// draw come before their automatic counterparts
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    // the same position for the third time, the side to move may claim a draw
+    ThreefoldRepetition,
+    // fifty moves by each side without a capture or pawn move, may be claimed
+    FiftyMoveRule,
+    // the same position for the fifth time, drawn without a claim
+    FivefoldRepetition,
+    // seventy-five moves without a capture or pawn move, drawn without a claim
+    SeventyFiveMoveRule,
+}
+
+impl DrawReason {
+    // the game ends by itself instead of on a player's claim
+    pub fn is_automatic(&self) -> bool {
+        !matches!(self, DrawReason::ThreefoldRepetition | DrawReason::FiftyMoveRule)
+    }
+}
+
+// game ended from outside the board
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Adjudication {
+    Resignation,
+    TimeForfeit,
+    DrawAgreement,
+    // a draw claimed under the threefold repetition or fifty-move rule
+    DrawClaim(DrawReason),
+    // decided by an arbiter or engine adjudication rules, eg. a long run of lopsided scores
+    Arbiter,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Checkmate { winner: Colour },
+    Draw(DrawReason),
+    Adjudicated { result: GameResult, reason: Adjudication },
+}
+
+impl Outcome {
+    pub fn result(&self) -> GameResult {
+        match self {
+            Outcome::Checkmate { winner: Colour::White } => GameResult::WhiteWins,
+            Outcome::Checkmate { .. } => GameResult::BlackWins,
+            Outcome::Draw(_) => GameResult::Draw,
+            Outcome::Adjudicated { result, .. } => *result,
+        }
+    }
+
+    pub fn winner(&self) -> Option<Colour> {
+        match self.result() {
+            GameResult::WhiteWins => Some(Colour::White),
+            GameResult::BlackWins => Some(Colour::Black),
+            _ => None,
+        }
+    }
+
+    // false for draws that a player may claim but has not
+    pub fn is_final(&self) -> bool {
+        match self {
+            Outcome::Draw(reason) => reason.is_automatic(),
+            _ => true,
+        }
+    }
+
+    // value of the PGN Termination tag
+    pub fn termination(&self) -> &'static str {
+        match self {
+            Outcome::Adjudicated { reason: Adjudication::TimeForfeit, .. } => "time forfeit",
+            Outcome::Adjudicated { reason: Adjudication::Arbiter, .. } => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+// eg. "1-0 (checkmate)" or "1/2-1/2 (threefold repetition)"
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let draw = |reason: &DrawReason| match reason {
+            DrawReason::Stalemate => "stalemate",
+            DrawReason::InsufficientMaterial => "insufficient material",
+            DrawReason::ThreefoldRepetition => "threefold repetition",
+            DrawReason::FiftyMoveRule => "fifty-move rule",
+            DrawReason::FivefoldRepetition => "fivefold repetition",
+            DrawReason::SeventyFiveMoveRule => "seventy-five-move rule",
+        };
+        let reason = match self {
+            Outcome::Checkmate { .. } => "checkmate",
+            Outcome::Draw(reason) => draw(reason),
+            Outcome::Adjudicated { reason, .. } => match reason {
+                Adjudication::Resignation => "resignation",
+                Adjudication::TimeForfeit => "time forfeit",
+                Adjudication::DrawAgreement => "agreement",
+                Adjudication::DrawClaim(reason) => draw(reason),
+                Adjudication::Arbiter => "adjudication",
+            },
+        };
+        write!(f, "{} ({})", self.result(), reason)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameError {
+    IllegalMove(Move),
+    // no more moves after checkmate, an automatic draw or an adjudication
+    GameOver(Outcome),
+    // claim_draw without threefold repetition or fifty moves
+    NoDrawToClaim,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::IllegalMove(mv) => write!(f, "{} is not a legal move", mv),
+            GameError::GameOver(outcome) => write!(f, "the game is over: {}", outcome),
+            GameError::NoDrawToClaim => write!(f, "there is no draw to claim"),
+        }
+    }
+}
+
+impl Error for GameError {}
+
+// a game from a start position, the position keeps the hashes of every position since the start
+// for repetition detection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    start: Position,
+    position: Position,
+    moves: Vec<Move>,
+    // moves taken back, the last one is replayed first
+    undone: Vec<Move>,
+    adjudication: Option<Outcome>,
+}
+
+impl Game {
+    pub fn new(start: Position) -> Game {
+        Game {
+            position: start.clone(),
+            start,
+            moves: Vec::new(),
+            undone: Vec::new(),
+            adjudication: None,
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        Ok(Game::new(Position::from_fen(fen)?))
+    }
+
+    pub fn start(&self) -> &Position {
+        &self.start
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn play(&mut self, mv: Move) -> Result<(), GameError> {
+        if let Some(outcome) = self.outcome().filter(Outcome::is_final) {
+            return Err(GameError::GameOver(outcome));
+        }
+        if !generate_legal_moves(&self.position).contains(&mv) {
+            return Err(GameError::IllegalMove(mv));
+        }
+        self.position.make_move(mv);
+        self.moves.push(mv);
+        self.undone.clear();
+        Ok(())
+    }
+
+    // take back the last move, this also lifts any adjudication
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.position.unmake_move(mv);
+        self.undone.push(mv);
+        self.adjudication = None;
+        Some(mv)
+    }
+
+    // play the last move taken back again, until a different move is played
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = self.undone.pop()?;
+        self.position.make_move(mv);
+        self.moves.push(mv);
+        self.adjudication = None;
+        Some(mv)
+    }
+
+    pub fn resign(&mut self, colour: Colour) -> Result<(), GameError> {
+        let result = match colour {
+            Colour::White => GameResult::BlackWins,
+            _ => GameResult::WhiteWins,
+        };
+        self.adjudicate(result, Adjudication::Resignation)
+    }
+
+    // the flag of a side fell, a draw if the opponent cannot checkmate by any series of legal
+    // moves
+    pub fn time_forfeit(&mut self, colour: Colour) -> Result<(), GameError> {
+        let opponent = colour.opposite();
+        let result = if self.position.insufficient_material_for(opponent) {
+            GameResult::Draw
+        } else if opponent == Colour::White {
+            GameResult::WhiteWins
+        } else {
+            GameResult::BlackWins
+        };
+        self.adjudicate(result, Adjudication::TimeForfeit)
+    }
+
+    pub fn agree_draw(&mut self) -> Result<(), GameError> {
+        self.adjudicate(GameResult::Draw, Adjudication::DrawAgreement)
+    }
+
+    // end the game with a threefold repetition or fifty-move draw
+    pub fn claim_draw(&mut self) -> Result<(), GameError> {
+        match self.outcome() {
+            Some(Outcome::Draw(reason)) if !reason.is_automatic() => {
+                self.adjudicate(GameResult::Draw, Adjudication::DrawClaim(reason))
+            }
+            Some(outcome) if outcome.is_final() => Err(GameError::GameOver(outcome)),
+            _ => Err(GameError::NoDrawToClaim),
+        }
+    }
+
+    // end a game that is still going on with the given result
+    pub fn adjudicate(
+        &mut self,
+        result: GameResult,
+        reason: Adjudication,
+    ) -> Result<(), GameError> {
+        if let Some(outcome) = self.outcome().filter(Outcome::is_final) {
+            return Err(GameError::GameOver(outcome));
+        }
+        self.adjudication = Some(Outcome::Adjudicated { result, reason });
+        Ok(())
+    }
+
+    // result string for PGN, "*" while the game goes on
+    pub fn result(&self) -> GameResult {
+        match self.outcome().filter(Outcome::is_final) {
+            Some(outcome) => outcome.result(),
+            None => GameResult::Ongoing,
+        }
+    }
+
+    // the game so far with its result, ready to be written as PGN
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut game = PgnGame::new(self.start.clone());
+        game.moves = self.moves.iter().map(|mv| PgnNode::new(*mv)).collect();
+        game.result = self.result();
+        if let Some(outcome) = self.outcome().filter(Outcome::is_final) {
+            game.set_tag("Termination", outcome.termination());
+        }
+        game
+    }
+
+    // how the game ended, None while it goes on. Draws that have to be claimed count as well,
+    // see Outcome::is_final
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.adjudication.is_some() {
+            return self.adjudication;
+        }
+        let position = &self.position;
+
+        if generate_legal_moves(position).is_empty() {
+            return Some(if position.in_check() {
+                Outcome::Checkmate { winner: position.side_to_move.opposite() }
+            } else {
+                Outcome::Draw(DrawReason::Stalemate)
+            });
+        }
+
+        let repetitions = position.repetitions();
+        let reason = if position.insufficient_material() {
+            DrawReason::InsufficientMaterial
+        } else if repetitions >= 4 {
+            DrawReason::FivefoldRepetition
+        } else if position.half_move_clock >= 150 {
+            DrawReason::SeventyFiveMoveRule
+        } else if repetitions >= 2 {
+            DrawReason::ThreefoldRepetition
+        } else if position.half_move_clock >= 100 {
+            DrawReason::FiftyMoveRule
+        } else {
+            return None;
+        };
+        Some(Outcome::Draw(reason))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen;
+    use crate::game::*;
+
+    fn play(game: &mut Game, moves: &str) {
+        for san in moves.split_whitespace() {
+            game.play(game.position().parse_san(san).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn check_repetition() {
+        let mut game = Game::from_fen(fen::START_POS).unwrap();
+        play(&mut game, "Nf3 Nf6 Ng1 Ng8");
+        assert_eq!(game.position().repetitions(), 1);
+        assert_eq!(game.outcome(), None);
+
+        play(&mut game, "Nf3 Nf6 Ng1 Ng8");
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::ThreefoldRepetition)));
+
+        play(&mut game, "Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8");
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::FivefoldRepetition)));
+
+        let fivefold = Outcome::Draw(DrawReason::FivefoldRepetition);
+        let e4 = game.position().parse_san("e4").unwrap();
+        assert_eq!(game.play(e4), Err(GameError::GameOver(fivefold)));
+
+        // a pawn move starts over
+        game.undo();
+        play(&mut game, "e5");
+        assert_eq!(game.position().repetitions(), 0);
+        assert_eq!(game.play(e4), Ok(()));
+        assert_eq!(game.play(e4), Err(GameError::IllegalMove(e4)));
+    }
+
+    #[test]
+    fn check_move_rules() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/R7/4K3 w - - 99 80").unwrap();
+        assert_eq!(game.outcome(), None);
+        play(&mut game, "Ra3");
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::FiftyMoveRule)));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/R7/4K3 w - - 150 100").unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule)));
+
+        // mate on the fiftieth move still counts
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 99 80").unwrap();
+        play(&mut game, "Ra8#");
+        assert_eq!(game.outcome(), Some(Outcome::Checkmate { winner: Colour::White }));
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::Stalemate)));
+    }
+
+    #[test]
+    fn check_game_end() {
+        let mut game = Game::from_fen(fen::START_POS).unwrap();
+        play(&mut game, "f3 e5 g4 Qh4#");
+        let mate = Outcome::Checkmate { winner: Colour::Black };
+        assert_eq!(game.outcome(), Some(mate));
+        assert_eq!(game.result().to_string(), "0-1");
+        assert_eq!(mate.to_string(), "0-1 (checkmate)");
+
+        let e4 = Position::from_fen(fen::START_POS).unwrap().parse_san("e4").unwrap();
+        assert_eq!(game.play(e4), Err(GameError::GameOver(mate)));
+        assert_eq!(game.resign(Colour::Black), Err(GameError::GameOver(mate)));
+
+        // take back the mate and play on
+        let qh4 = game.undo().unwrap();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.redo(), Some(qh4));
+        assert_eq!(game.undo(), Some(qh4));
+        play(&mut game, "Nc6");
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.moves().len(), 4);
+
+        game.resign(Colour::White).unwrap();
+        assert_eq!(game.result(), GameResult::BlackWins);
+        assert_eq!(game.outcome().unwrap().to_string(), "0-1 (resignation)");
+        let pgn = game.to_pgn().to_pgn();
+        assert!(pgn.contains("[Termination \"normal\"]"));
+        assert!(pgn.ends_with("1. f3 e5 2. g4 Nc6 0-1\n"));
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+
+        // a fallen flag against a bare king is a draw
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        game.time_forfeit(Colour::White).unwrap();
+        assert_eq!(game.result(), GameResult::Draw);
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        game.time_forfeit(Colour::Black).unwrap();
+        assert_eq!(game.outcome().unwrap().termination(), "time forfeit");
+        assert_eq!(game.result(), GameResult::WhiteWins);
+
+        // a lone knight cannot mate a bare king, so that game is already drawn, but it can mate
+        // with a pawn in the way
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/1N2K3 b - - 0 1").unwrap();
+        assert_eq!(
+            game.time_forfeit(Colour::Black),
+            Err(GameError::GameOver(Outcome::Draw(DrawReason::InsufficientMaterial)))
+        );
+        let mut game = Game::from_fen("4k3/4p3/8/8/8/8/8/1N2K3 b - - 0 1").unwrap();
+        game.time_forfeit(Colour::Black).unwrap();
+        assert_eq!(game.result(), GameResult::WhiteWins);
+        let mut game = Game::from_fen("4k3/4p3/8/8/8/8/8/1N2K3 b - - 0 1").unwrap();
+        game.time_forfeit(Colour::White).unwrap();
+        assert_eq!(game.result(), GameResult::BlackWins);
+
+        // threefold repetition has to be claimed
+        let mut game = Game::from_fen(fen::START_POS).unwrap();
+        assert_eq!(game.claim_draw(), Err(GameError::NoDrawToClaim));
+        play(&mut game, "Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8");
+        assert_eq!(game.result(), GameResult::Ongoing);
+        game.claim_draw().unwrap();
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.outcome().unwrap().to_string(), "1/2-1/2 (threefold repetition)");
+    }
+
+    #[test]
+    fn check_insufficient_material() {
+        let drawn = |fen: &str| Position::from_fen(fen).unwrap().insufficient_material();
+
+        assert!(drawn("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(drawn("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"));
+        assert!(drawn("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(drawn("1b2k3/8/8/8/8/8/1B6/4K1B1 w - - 0 1"));
+
+        assert!(!drawn("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1"));
+        assert!(!drawn("4kn2/8/8/8/8/8/8/4KN2 w - - 0 1"));
+        assert!(!drawn("4k3/8/8/8/8/8/8/4KNN1 w - - 0 1"));
+        assert!(!drawn("4k3/8/8/8/8/8/P7/4K3 w - - 0 1"));
+
+        // only the side with the pawn can mate, the knight needs the pawn to block
+        let position = Position::from_fen("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+        assert!(!position.insufficient_material_for(Colour::White));
+        assert!(position.insufficient_material_for(Colour::Black));
+        let position = Position::from_fen("4k3/8/8/8/8/8/P7/4K1n1 w - - 0 1").unwrap();
+        assert!(!position.insufficient_material_for(Colour::Black));
+    }
+}
@@ -1,32 +1,47 @@
-use crate::{bitboard::*, movegen::*, utils::match_u32_to_sq};
+use crate::{bitboard::*, movegen::*};
 use movegen::{king, knight, pawn};
 
 pub mod bitboard;
+pub mod chess960;
+pub mod eval;
 pub mod fen;
+pub mod game;
 pub mod movegen;
+pub mod pawns;
+pub mod perft;
+pub mod pgn;
+pub mod position;
+pub mod san;
+pub mod search;
+pub mod see;
+pub mod square;
 pub mod utils;
+pub mod validate;
+pub mod zobrist;
+
+pub use square::{File, Rank, Square};
 
 // piece enum, eg. black bishop [kind: Bishop, colour: Black, symbol: 'b']
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Piece {
     pub kind: Kind,
     pub colour: Colour,
     pub symbol: char,
 }
 
-// piece type
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+// piece type, ordered to match the bitboard layout used by `bitboard::convert`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Kind {
-    King,
-    Queen,
-    Bishop,
+    Pawn,
     Knight,
+    Bishop,
     Rook,
-    Pawn,
+    Queen,
+    King,
 }
 
 // colour / side to move
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Colour {
     Black,
     White,
@@ -37,10 +52,14 @@ pub enum Colour {
 pub struct GameStatus {
     pub pieces: [Option<Piece>; 64],
     pub side_to_move: Colour,
-    pub castling_id: [bool; 4],
+    // file of the castling rook for [white kingside, white queenside, black kingside, black
+    // queenside], None once the right is lost
+    pub castling_id: [Option<usize>; 4],
     pub en_passant: Option<Vec<Square>>,
     pub half_move_clock: u16,
     pub full_move_count: u16,
+    // castling rights that need Chess960 rules (king or rook away from the e, a and h files)
+    pub chess960: bool,
 }
 
 impl GameStatus {
@@ -48,15 +67,62 @@ impl GameStatus {
         GameStatus {
             pieces: [None; 64],
             side_to_move: Colour::White,
-            castling_id: [true, true, true, true],
+            castling_id: [Some(7), Some(0), Some(7), Some(0)],
             en_passant: None,
             half_move_clock: 0,
             full_move_count: 1,
+            chess960: false,
+        }
+    }
+}
+
+impl Colour {
+    // the other side
+    pub fn opposite(&self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+            Colour::Undefined => Colour::Undefined,
+        }
+    }
+}
+
+impl Kind {
+    // lowercase letter used in FEN and UCI notation
+    pub fn to_char(&self) -> char {
+        match self {
+            Kind::Pawn => 'p',
+            Kind::Knight => 'n',
+            Kind::Bishop => 'b',
+            Kind::Rook => 'r',
+            Kind::Queen => 'q',
+            Kind::King => 'k',
+        }
+    }
+    // piece type from a FEN letter of either colour
+    pub fn from_char(c: char) -> Option<Kind> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(Kind::Pawn),
+            'n' => Some(Kind::Knight),
+            'b' => Some(Kind::Bishop),
+            'r' => Some(Kind::Rook),
+            'q' => Some(Kind::Queen),
+            'k' => Some(Kind::King),
+            _ => None,
         }
     }
 }
 
 impl Piece {
+    pub fn new(kind: Kind, colour: Colour) -> Piece {
+        let symbol = match colour {
+            Colour::White => kind.to_char().to_ascii_uppercase(),
+            _ => kind.to_char(),
+        };
+
+        Piece { kind, colour, symbol }
+    }
+
     fn from_char(piece_char: char) -> Option<Piece> {
         let (colour, kind) = match piece_char {
             'P' => (Colour::White, Kind::Pawn),
@@ -78,28 +144,10 @@ impl Piece {
     }
 }
 
-// to represent squares
-#[rustfmt::skip]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum Square {
-    A8, B8, C8, D8, E8, F8, G8, H8,
-    A7, B7, C7, D7, E7, F7, G7, H7,
-    A6, B6, C6, D6, E6, F6, G6, H6,
-    A5, B5, C5, D5, E5, F5, G5, H5,
-    A4, B4, C4, D4, E4, F4, G4, H4,
-    A3, B3, C3, D3, E3, F3, G3, H3,
-    A2, B2, C2, D2, E2, F2, G2, H2,
-    A1, B1, C1, D1, E1, F1, G1, H1,
-}
-
 // return all pieces on the board except one of a particular piece type
 pub fn all_other_pieces(pieces: [[BitBoard; 6]; 2], sub: BitBoard) -> BitBoard {
-    let mut all_other_pieces = BitBoard::empty();
-    // union with all pieces on the board
-    all_other_pieces.0 |= occupancies(pieces, Colour::Undefined).0;
-    // subtract intersection of all pieces with sub (aka the pieces we want to remove)
-    all_other_pieces.0 -= all_other_pieces.0 & sub.0;
-    all_other_pieces
+    // all pieces on the board without the ones we want to remove
+    occupancies(pieces, Colour::Undefined) & !sub
 }
 
 pub fn get_attacks(side: Colour, pieces: [[BitBoard; 6]; 2]) -> BitBoard {
@@ -118,36 +166,25 @@ pub fn get_attacks(side: Colour, pieces: [[BitBoard; 6]; 2]) -> BitBoard {
     let king_attacks = king::all(king, BitBoard::empty());
     let queen_attacks = queen_bb(queen, all_other_pieces(pieces, queen));
 
-    BitBoard(
-        pawn_attacks.0
-            | knight_attacks.0
-            | bishop_attacks.0
-            | rook_attacks.0
-            | king_attacks.0
-            | queen_attacks.0,
-    )
+    pawn_attacks | knight_attacks | bishop_attacks | rook_attacks | king_attacks | queen_attacks
 }
 
 pub fn get_all_attacks(pieces: [[BitBoard; 6]; 2]) -> BitBoard {
     let white_attacks = get_attacks(Colour::White, pieces);
     let black_attacks = get_attacks(Colour::Black, pieces);
 
-    BitBoard(white_attacks.0 | black_attacks.0)
+    white_attacks | black_attacks
 }
 
 impl Square {
     // check if square is attacked
     pub fn is_attacked(&self, side: Colour, pieces: [[BitBoard; 6]; 2]) -> bool {
-        //println!("Square: {:?}, Side: {:?}", self, side);
-        let occ = BitBoard::from_sq(*self);
-        // return true or false depending on if the square is attacked (check if it intersects with
-        // bitboard containing all attacks)
-        get_attacks(side, pieces).0 & occ.0 != 0
+        is_attacked_by(*self, side, &pieces, occupancies(pieces, Colour::Undefined))
     }
 }
 
 pub fn init() {
-    let mut game_state = fen::return_state("3r4/1b6/3r4/R2P4/8/8/2k5/3R4 w - - 0 0");
+    let mut game_state = fen::return_state("3r4/1b6/3r4/R2P4/8/8/2k5/3R4 w - - 0 0").unwrap();
     //let mut game_state = fen::return_state(fen::START_POS);
     fen::print_all(&game_state);
     println!();
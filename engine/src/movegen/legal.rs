@@ -0,0 +1,324 @@
+use crate::bitboard::*;
+use crate::movegen::moves::*;
+use crate::movegen::*;
+use crate::position::Position;
+use crate::{Colour, Kind, Square};
+
+const PROMOTIONS: [Kind; 4] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight];
+
+// generate all legal moves for the side to move
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    let mut list = MoveList::new();
+    let checkers = position.checkers();
+
+    if checkers.is_empty() {
+        let own = position.occupancy[position.side_to_move as usize];
+        generate_moves(position, !own, &mut list);
+        king_moves(position, &mut list);
+        castling_moves(position, position.occupancy[2], &mut list);
+    } else {
+        generate_evasions(position, checkers, &mut list);
+    }
+    list
+}
+
+// legal moves when the side to move is in check: king moves, and with a single checker also
+// captures of the checker and moves blocking the line between it and the king
+pub fn generate_evasions(position: &Position, checkers: BitBoard, list: &mut MoveList) {
+    king_moves(position, list);
+
+    // only the king can escape a double check
+    if checkers.count_bits() > 1 {
+        return;
+    }
+
+    let king = position.king_square(position.side_to_move).expect("a king in check");
+    let checker = checkers.lsb().expect("one checker");
+    generate_moves(position, rays::between(king, checker) | checkers, list);
+}
+
+// pawn, knight and slider moves ending on one of the target squares, pinned pieces only move
+// along their pin ray
+fn generate_moves(position: &Position, targets: BitBoard, list: &mut MoveList) {
+    let side = position.side_to_move;
+    let own = position.occupancy[side as usize];
+    let occ = position.occupancy[2];
+    let king = position.king_square(side);
+    let pinned = position.pinned(side);
+
+    pawn_moves(position, targets, pinned, list);
+
+    for kind in [Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen] {
+        for from in position.pieces_of(side, kind) {
+            let mut attacks = match kind {
+                Kind::Knight => knight::lookup(from),
+                Kind::Bishop => bishop(from, occ),
+                Kind::Rook => rook(from, occ),
+                _ => queen(from, occ),
+            };
+            attacks &= !own & targets;
+
+            // only pieces of a side with a king can be pinned
+            if let Some(king) = king.filter(|_| pinned.get_bit(from)) {
+                attacks &= rays::line(king, from);
+            }
+
+            add_moves(position, from, kind, attacks, list);
+        }
+    }
+}
+
+// king steps to squares not attacked by the enemy, with the king itself removed from the
+// occupancy so that it cannot hide behind itself on the line of a slider
+fn king_moves(position: &Position, list: &mut MoveList) {
+    let side = position.side_to_move;
+    let from = match position.king_square(side) {
+        Some(king) => king,
+        None => return,
+    };
+    let occ = position.occupancy[2] & !BitBoard::from_sq(from);
+
+    for to in king::lookup(from) & !position.occupancy[side as usize] {
+        if !is_attacked_by(to, side.opposite(), &position.bitboards, occ) {
+            let capture = position.piece_at(to).map(|piece| piece.kind);
+            list.push(Move::new(from, to, Kind::King, capture));
+        }
+    }
+}
+
+// add a move for every target square, looking up captured pieces in the mailbox
+fn add_moves(
+    position: &Position,
+    from: Square,
+    kind: Kind,
+    targets: BitBoard,
+    list: &mut MoveList,
+) {
+    for to in targets {
+        let capture = position.piece_at(to).map(|piece| piece.kind);
+        list.push(Move::new(from, to, kind, capture));
+    }
+}
+
+// add a pawn move, expanding it into all four promotions on the last rank
+fn add_pawn_move(mv: Move, list: &mut MoveList) {
+    let last_rank = RANKS[0] | RANKS[7];
+
+    if last_rank.get_bit(mv.to) {
+        for kind in PROMOTIONS {
+            list.push(Move { promotion: Some(kind), ..mv });
+        }
+    } else {
+        list.push(mv);
+    }
+}
+
+fn pawn_moves(position: &Position, targets: BitBoard, pinned: BitBoard, list: &mut MoveList) {
+    let side = position.side_to_move;
+    let enemy = position.occupancy[side.opposite() as usize];
+    let occ = position.occupancy[2];
+    let king = position.king_square(side);
+    let pawns = position.pieces_of(side, Kind::Pawn);
+
+    // white pawns move towards the 8th rank
+    let (forward, start_rank): (i32, BitBoard) = match side {
+        Colour::White => (1, RANKS[6]),
+        _ => (-1, RANKS[1]),
+    };
+
+    for from in pawns {
+        let allowed = match king.filter(|_| pinned.get_bit(from)) {
+            Some(king) => targets & rays::line(king, from),
+            None => targets,
+        };
+
+        // single and double pushes
+        let single = from.offset(0, forward).filter(|sq| !occ.get_bit(*sq));
+        if let Some(single) = single {
+            if allowed.get_bit(single) {
+                add_pawn_move(Move::new(from, single, Kind::Pawn, None), list);
+            }
+
+            if start_rank.get_bit(from) {
+                let double = single.offset(0, forward).expect("pawn on its start rank");
+                if !occ.get_bit(double) && allowed.get_bit(double) {
+                    let mv = Move::new(from, double, Kind::Pawn, None);
+                    list.push(Move { flag: MoveFlag::DoublePush, ..mv });
+                }
+            }
+        }
+
+        // captures
+        let attacks = pawn::lookup(from, side);
+        for to in attacks & enemy & allowed {
+            let capture = position.piece_at(to).map(|piece| piece.kind);
+            add_pawn_move(Move::new(from, to, Kind::Pawn, capture), list);
+        }
+
+        // en passant, checked by playing it since removing two pawns from one rank can
+        // uncover an attack on the king that no pin mask catches
+        if let Some(ep) = position.en_passant {
+            if attacks.get_bit(ep) && position.piece_at(ep).is_none() {
+                let mv = Move::new(from, ep, Kind::Pawn, Some(Kind::Pawn));
+                let mv = Move { flag: MoveFlag::EnPassant, ..mv };
+
+                // the square may come from a FEN without a pawn that just moved past it
+                let victim = en_passant_victim(&mv);
+                if !position.pieces_of(side.opposite(), Kind::Pawn).get_bit(victim) {
+                    continue;
+                }
+
+                let resolves = targets.get_bit(ep) || targets.get_bit(victim);
+                if resolves && !leaves_king_in_check(position.bitboards, side, &mv) {
+                    list.push(mv);
+                }
+            }
+        }
+    }
+}
+
+// castling for standard chess and Chess960, the king and rook may start on any file and land on
+// squares the other one starts on
+fn castling_moves(position: &Position, occ: BitBoard, list: &mut MoveList) {
+    let side = position.side_to_move;
+    let enemy = side.opposite();
+    let king_from = match position.king_square(side) {
+        Some(king) => king,
+        None => return,
+    };
+    let rights = if side == Colour::White { [0, 1] } else { [2, 3] };
+
+    for right in rights {
+        let rook_from = match position.castling_rook_square(right) {
+            Some(sq) if position.castling_intact(right) => sq,
+            _ => continue,
+        };
+        let (king_to, rook_to) = castling_squares(king_from, rook_from);
+        let castlers = BitBoard::from_sq(king_from) | BitBoard::from_sq(rook_from);
+
+        // everything both pieces pass over or land on must be empty, apart from themselves
+        let path = rays::between(king_from, king_to)
+            | rays::between(rook_from, rook_to)
+            | BitBoard::from_sq(king_to)
+            | BitBoard::from_sq(rook_to);
+        if !(path & occ & !castlers).is_empty() {
+            continue;
+        }
+
+        // the king may not pass through an attacked square
+        let safe = rays::between(king_from, king_to)
+            .into_iter()
+            .all(|sq| !is_attacked_by(sq, enemy, &position.bitboards, occ));
+
+        // the destination is tested after the rook moved, as it may have been shielding the king
+        // along the back rank
+        let after = (occ & !castlers) | BitBoard::from_sq(rook_to);
+        if !safe || is_attacked_by(king_to, enemy, &position.bitboards, after) {
+            continue;
+        }
+
+        let mv = Move::new(king_from, rook_from, Kind::King, None);
+        list.push(Move { flag: MoveFlag::Castle, ..mv });
+    }
+}
+
+// square of the pawn captured by an en passant move, next to the capturing pawn
+pub fn en_passant_victim(mv: &Move) -> Square {
+    Square::new(mv.to.file(), mv.from.rank())
+}
+
+// play the move on a copy of the bitboards and check if the own king is attacked afterwards
+fn leaves_king_in_check(mut pieces: [[BitBoard; 6]; 2], side: Colour, mv: &Move) -> bool {
+    let us = side as usize;
+    let them = side.opposite() as usize;
+
+    pieces[us][mv.piece as usize].toggle_bit(mv.from);
+    pieces[us][mv.promotion.unwrap_or(mv.piece) as usize].set_bit(mv.to);
+
+    if let Some(kind) = mv.capture {
+        let victim = match mv.flag {
+            MoveFlag::EnPassant => en_passant_victim(mv),
+            _ => mv.to,
+        };
+        pieces[them][kind as usize].toggle_bit(victim);
+    }
+
+    let king = match pieces[us][Kind::King as usize].lsb() {
+        Some(king) => king,
+        None => return false,
+    };
+    is_attacked_by(king, side.opposite(), &pieces, occupancies(pieces, Colour::Undefined))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen;
+    use crate::movegen::legal::*;
+
+    fn perft(position: &mut Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut nodes = 0;
+        for mv in &generate_legal_moves(position) {
+            position.make_move(*mv);
+            nodes += perft(position, depth - 1);
+            position.unmake_move(*mv);
+        }
+        nodes
+    }
+
+    #[test]
+    fn check_legal_move_counts() {
+        let positions = [
+            (fen::START_POS, [20, 400, 8902]),
+            (fen::TRICKY_POS, [48, 2039, 97862]),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", [14, 191, 2812]),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", [6, 264, 9467]),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", [44, 1486, 62379]),
+        ];
+
+        for (fen, counts) in positions {
+            let mut position = Position::from_fen(fen).unwrap();
+            for (depth, count) in counts.iter().enumerate() {
+                let nodes = perft(&mut position, depth as u32 + 1);
+                assert_eq!(nodes, *count, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn check_kingless_positions() {
+        // no king moves out of an empty square, and no check for a side without a king
+        let mut position = Position::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").unwrap();
+        assert!(!position.in_check());
+        assert_eq!(perft(&mut position, 1), 0);
+
+        let mut position = Position::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&mut position, 1), 0);
+
+        let mut position = Position::from_fen("r3k3/8/8/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert!(!position.in_check());
+        assert_eq!(position.pinned(Colour::White), BitBoard::empty());
+        assert_eq!(perft(&mut position, 1), 14);
+    }
+
+    #[test]
+    fn check_en_passant_without_victim() {
+        // e6 is no en passant square with e5 empty or holding a knight, set by hand since FEN
+        // parsing drops it
+        for fen in ["4k3/8/8/3P4/8/8/8/4K3 w - - 0 1", "4k3/8/8/3Pn3/8/8/8/4K3 w - - 0 1"] {
+            let mut position = Position::from_fen(fen).unwrap();
+            position.en_passant = Some(Square::E6);
+            position.hash = position.compute_hash();
+            let before = position.clone();
+
+            assert!(generate_legal_moves(&position)
+                .iter()
+                .all(|mv| mv.flag != MoveFlag::EnPassant));
+            perft(&mut position, 2);
+            assert_eq!(position, before, "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::bitboard::*;
+use crate::movegen::{bishop_hq, rook_hq};
+use crate::Square;
+use std::sync::OnceLock;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Slider {
+    Rook,
+    Bishop,
+}
+
+// magic lookup for one square: index = ((occ & mask) * magic) >> shift
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Magic {
+    pub mask: u64,
+    pub magic: u64,
+    pub shift: u32,
+    pub offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occ: BitBoard) -> usize {
+        self.offset + ((occ.0 & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    attacks: Vec<u64>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+// attacks on an empty board without the edge squares, since a blocker on the edge never changes
+// the attack set
+pub fn mask(sq: Square, slider: Slider) -> u64 {
+    let rank = sq as usize / 8;
+    let file = sq as usize % 8;
+
+    match slider {
+        Slider::Rook => {
+            let file_mask = FILES[file].0 & !(RANKS[0].0 | RANKS[7].0);
+            let rank_mask = RANKS[rank].0 & !(FILES[0].0 | FILES[7].0);
+            (file_mask | rank_mask) & !BitBoard::from_sq(sq).0
+        }
+        Slider::Bishop => {
+            let edges = RANKS[0].0 | RANKS[7].0 | FILES[0].0 | FILES[7].0;
+            bishop_hq(sq, BitBoard::empty()).0 & !edges
+        }
+    }
+}
+
+fn reference(sq: Square, occ: BitBoard, slider: Slider) -> BitBoard {
+    match slider {
+        Slider::Rook => rook_hq(sq, occ),
+        Slider::Bishop => bishop_hq(sq, occ),
+    }
+}
+
+// all subsets of the mask, enumerated with the carry-rippler trick
+fn subsets(mask: u64) -> Vec<u64> {
+    let mut subsets = Vec::with_capacity(1 << mask.count_ones());
+    let mut sub: u64 = 0;
+
+    loop {
+        subsets.push(sub);
+        sub = sub.wrapping_sub(mask) & mask;
+        if sub == 0 {
+            break;
+        }
+    }
+    subsets
+}
+
+// xorshift64
+fn random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// search for a magic that maps every relevant occupancy of the square to an index without
+// destructive collisions, trying random numbers with few bits set
+pub fn find_magic(sq: Square, slider: Slider, seed: &mut u64) -> u64 {
+    let mask = mask(sq, slider);
+    let bits = mask.count_ones();
+    let occupancies = subsets(mask);
+    let attacks: Vec<u64> =
+        occupancies.iter().map(|occ| reference(sq, BitBoard(*occ), slider).0).collect();
+
+    let mut used = vec![0u64; 1 << bits];
+    let mut epoch = vec![0u32; 1 << bits];
+    let mut attempt = 0;
+
+    loop {
+        let magic = random(seed) & random(seed) & random(seed);
+        attempt += 1;
+
+        // cheap filter, good magics map the mask to many high bits
+        if (mask.wrapping_mul(magic) & 0xff00000000000000).count_ones() < 6 {
+            continue;
+        }
+
+        let mut ok = true;
+        for (occ, attack) in occupancies.iter().zip(&attacks) {
+            let index = (occ.wrapping_mul(magic) >> (64 - bits)) as usize;
+
+            if epoch[index] != attempt {
+                epoch[index] = attempt;
+                used[index] = *attack;
+            } else if used[index] != *attack {
+                ok = false;
+                break;
+            }
+        }
+
+        if ok {
+            return magic;
+        }
+    }
+}
+
+// find magics for every square, used to produce the constants below. Xorshift never leaves a
+// zero state, so the lowest bit of the seed is always set
+pub fn generate_magics(slider: Slider, seed: u64) -> [u64; 64] {
+    let mut state = seed | 1;
+    let mut magics = [0; 64];
+
+    for (sq, magic) in Square::ALL.into_iter().zip(magics.iter_mut()) {
+        *magic = find_magic(sq, slider, &mut state);
+    }
+    magics
+}
+
+// print magics in a form that can be pasted into this file
+pub fn print_magics(name: &str, magics: &[u64; 64]) {
+    println!("pub const {}: [u64; 64] = [", name);
+    for magic in magics {
+        println!("    0x{:016x},", magic);
+    }
+    println!("];");
+}
+
+fn build(slider: Slider, magics: &[u64; 64], attacks: &mut Vec<u64>) -> [Magic; 64] {
+    let mut table = [Magic::default(); 64];
+
+    for (i, entry) in table.iter_mut().enumerate() {
+        let sq = Square::ALL[i];
+        let mask = mask(sq, slider);
+        let bits = mask.count_ones();
+
+        *entry = Magic { mask, magic: magics[i], shift: 64 - bits, offset: attacks.len() };
+        attacks.resize(attacks.len() + (1 << bits), 0);
+
+        for occ in subsets(mask) {
+            let index = entry.index(BitBoard(occ));
+            attacks[index] = reference(sq, BitBoard(occ), slider).0;
+        }
+    }
+    table
+}
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let mut attacks = Vec::new();
+        let rook = build(Slider::Rook, &ROOK_MAGICS, &mut attacks);
+        let bishop = build(Slider::Bishop, &BISHOP_MAGICS, &mut attacks);
+
+        Tables { rook, bishop, attacks }
+    })
+}
+
+// fill the attack tables now instead of on the first lookup
+pub fn init() {
+    tables();
+}
+
+pub fn rook(sq: Square, occ: BitBoard) -> BitBoard {
+    let tables = tables();
+    BitBoard(tables.attacks[tables.rook[sq as usize].index(occ)])
+}
+
+pub fn bishop(sq: Square, occ: BitBoard) -> BitBoard {
+    let tables = tables();
+    BitBoard(tables.attacks[tables.bishop[sq as usize].index(occ)])
+}
+
+// found with generate_magics(slider, 0x6b616765), `kage magics` prints a fresh set
+#[rustfmt::skip]
+pub const ROOK_MAGICS: [u64; 64] = [
+    0x208012208003c000,
+    0x0140002000100148,
+    0x0080200080100008,
+    0x8100200409001001,
+    0x0100080100020410,
+    0x2100080100040002,
+    0x0b00020040841100,
+    0x0200110400204882,
+    0x04c0800040008026,
+    0x0010804000200080,
+    0x0900802000100088,
+    0x9001001000090020,
+    0x0004802400802800,
+    0x20d200080410b201,
+    0x1009000200010004,
+    0x0002000a00408114,
+    0x4100808000400020,
+    0x0000404010002000,
+    0x8206020010204882,
+    0x0400808010000805,
+    0x1081010004080010,
+    0x0009010008040002,
+    0xa024840008210210,
+    0x0008020000408104,
+    0x0080400180008222,
+    0x1020002040100044,
+    0x0041001100402002,
+    0x1001010900201002,
+    0x0802001200090420,
+    0x0006000200100804,
+    0x0080020400500108,
+    0x6000008200004401,
+    0x0080014000c02002,
+    0x4010400094802000,
+    0x3080110041002000,
+    0x0022002012000c40,
+    0x0100110005000800,
+    0x4030800400800201,
+    0x0c00488104004210,
+    0x0000830242001c84,
+    0x0000400080008020,
+    0x0000402010004000,
+    0x481000a40800a000,
+    0x0000100a00420020,
+    0x8298000402004040,
+    0x0000020004008080,
+    0x0000c21801140010,
+    0x040109008062000c,
+    0x148000e240198080,
+    0x2000804000200480,
+    0x0010080400200020,
+    0x80042152402a0200,
+    0x0000408021001002,
+    0x4402000400028080,
+    0x0008881002010400,
+    0xc900042090410200,
+    0x0000800840902301,
+    0x900a810120d04001,
+    0x40c6000810204082,
+    0x0004200500100009,
+    0x804200c820500402,
+    0xa001002812040005,
+    0x0081010200881044,
+    0x0009090408214282,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_MAGICS: [u64; 64] = [
+    0x2014042884040088,
+    0x00082800a4004000,
+    0x10c9044400808080,
+    0x20a4504201001000,
+    0x48c2021000400200,
+    0x00084410040000c0,
+    0x0802021082088009,
+    0x1200822388144003,
+    0x080440108a0a0066,
+    0x0014108212004200,
+    0x1082420411002a00,
+    0x2900044040800040,
+    0x0001020210100008,
+    0x0000008804400048,
+    0x042444020242202a,
+    0x0042910108010440,
+    0x00c0900610040100,
+    0x0822000810840094,
+    0x4001009000408104,
+    0x0000802802024048,
+    0x400c000884a01a01,
+    0x0001010020a01000,
+    0x2a04100044220824,
+    0x0100410104220100,
+    0x0021280111024800,
+    0x0204248002100400,
+    0x1004040402080010,
+    0x00200800010104a0,
+    0x0001004014004040,
+    0x812c00c811012000,
+    0x020322c401081842,
+    0x0a00410001440640,
+    0x0001051002401010,
+    0x0208023013080130,
+    0x0104060100420400,
+    0x0000400808008201,
+    0x0998020400201100,
+    0x00900100408a0066,
+    0x0002420264440403,
+    0x601c809201048200,
+    0x0011100804022000,
+    0x4204120104211000,
+    0x0004201050002800,
+    0x12a00a2011008800,
+    0x0421480100408406,
+    0x0001200400920300,
+    0x0008082804900060,
+    0x0084108200430202,
+    0x2404020111880124,
+    0x00c1052910020940,
+    0x0002a04228040000,
+    0x0100000020882060,
+    0x4880003012120000,
+    0x1200a08210024000,
+    0x4088428418060040,
+    0x1c04084244032000,
+    0x4020510419044005,
+    0x0410008400888460,
+    0x1020900a48441002,
+    0x2602001004420200,
+    0x1100001020442420,
+    0x5004042120020080,
+    0x0221100210010208,
+    0x4002089010808100,
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::movegen::magic::*;
+
+    #[test]
+    fn check_magic_against_hyperbola() {
+        let mut state = 0x9e3779b97f4a7c15;
+
+        for i in 0..3000 {
+            // sparse, half full and dense occupancies
+            let occ = BitBoard(match i % 3 {
+                0 => random(&mut state) & random(&mut state),
+                1 => random(&mut state),
+                _ => random(&mut state) | random(&mut state),
+            });
+
+            for sq in Square::ALL {
+                assert_eq!(rook(sq, occ), rook_hq(sq, occ), "rook on {:?}", sq);
+                assert_eq!(bishop(sq, occ), bishop_hq(sq, occ), "bishop on {:?}", sq);
+            }
+        }
+    }
+
+    #[test]
+    fn check_generated_magics() {
+        // a zero seed would keep the generator at zero forever
+        let magics = generate_magics(Slider::Bishop, 0);
+        let mut attacks = Vec::new();
+        let table = build(Slider::Bishop, &magics, &mut attacks);
+
+        for sq in Square::ALL {
+            for occ in subsets(mask(sq, Slider::Bishop)) {
+                let attack = attacks[table[sq as usize].index(BitBoard(occ))];
+                assert_eq!(attack, bishop_hq(sq, BitBoard(occ)).0, "bishop on {:?}", sq);
+            }
+        }
+    }
+}
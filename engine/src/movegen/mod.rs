@@ -3,11 +3,20 @@ use crate::*;
 
 pub mod king;
 pub mod knight;
+pub mod legal;
+pub mod magic;
+pub mod moves;
 pub mod pawn;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+pub mod pext;
+pub mod rays;
 
-// hyperbola quintessence
+pub use legal::generate_legal_moves;
+pub use moves::{castling_squares, Move, MoveFlag, MoveList};
+
+// hyperbola quintessence (the slider itself is never treated as a blocker)
 pub fn hyp_quint(sq: Square, occ: BitBoard, mask: u64) -> BitBoard {
-    let mut forward = occ.0 & mask;
+    let mut forward = occ.0 & mask & !BitBoard::from_sq(sq).0;
     let mut reverse = forward.reverse_bits();
 
     forward = forward.wrapping_sub(BitBoard::from_sq(sq).0);
@@ -18,89 +27,129 @@ pub fn hyp_quint(sq: Square, occ: BitBoard, mask: u64) -> BitBoard {
     BitBoard(forward)
 }
 
-// lookup rook attacks for a rook on a particular square
-pub fn rook(sq: Square, occ: BitBoard) -> BitBoard {
+// rook attacks with hyperbola quintessence, the reference for the table based lookups
+pub fn rook_hq(sq: Square, occ: BitBoard) -> BitBoard {
     let tr = sq as usize / 8;
     let tf = sq as usize % 8;
 
-    BitBoard(hyp_quint(sq, occ, FILES[tf].0).0 | hyp_quint(sq, occ, RANKS[tr].0).0)
+    hyp_quint(sq, occ, FILES[tf].0) | hyp_quint(sq, occ, RANKS[tr].0)
 }
 
-// lookup rook attacks for all bits (bitboard containing rook occupancies)
-pub fn rook_bb(board: BitBoard, occ: BitBoard) -> BitBoard {
-    let mut bb = BitBoard::empty();
+// slider attack lookups, PEXT when the cpu has BMI2 and magics otherwise. Picked once, so that
+// lookups do not check the cpu every time
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+struct Backend {
+    rook: unsafe fn(Square, BitBoard) -> BitBoard,
+    bishop: unsafe fn(Square, BitBoard) -> BitBoard,
+}
+
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+static BACKEND: std::sync::OnceLock<Backend> = std::sync::OnceLock::new();
 
-    for rank in 0..8 {
-        for file in 0..8 {
-            let square = rank * 8 + file;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+fn backend() -> &'static Backend {
+    BACKEND.get_or_init(|| match pext::available() {
+        true => Backend { rook: pext::rook, bishop: pext::bishop },
+        false => Backend { rook: magic::rook, bishop: magic::bishop },
+    })
+}
 
-            let tr = square as usize / 8;
-            let tf = square as usize % 8;
+// pick the slider backend and fill its attack tables now instead of on the first lookup
+pub fn init() {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    backend();
+    rook(Square::A1, BitBoard::empty());
+}
 
-            if board.0 & (1 << square) != 0 {
-                bb.0 |= hyp_quint(match_u32_to_sq(square), occ, FILES[tf].0).0;
-                bb.0 |= hyp_quint(match_u32_to_sq(square), occ, RANKS[tr].0).0;
-            }
+// lookup rook attacks for a rook on a particular square
+pub fn rook(sq: Square, occ: BitBoard) -> BitBoard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    {
+        // built with BMI2 enabled the PEXT lookup is called directly and can be inlined
+        if cfg!(target_feature = "bmi2") {
+            return unsafe { pext::rook(sq, occ) };
         }
+        // the PEXT functions are only picked when the cpu supports BMI2
+        unsafe { (backend().rook)(sq, occ) }
     }
-    bb
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+    magic::rook(sq, occ)
 }
 
-// lookup bishop attacks for a bishop on a particular square
-pub fn bishop(sq: Square, occ: BitBoard) -> BitBoard {
+// lookup rook attacks for all bits (bitboard containing rook occupancies)
+pub fn rook_bb(board: BitBoard, occ: BitBoard) -> BitBoard {
+    board.into_iter().fold(BitBoard::empty(), |bb, sq| bb | rook(sq, occ))
+}
+
+// bishop attacks with hyperbola quintessence, the reference for the table based lookups
+pub fn bishop_hq(sq: Square, occ: BitBoard) -> BitBoard {
     let tr = sq as usize / 8;
     let tf = sq as usize % 8;
 
     let diag_index: usize = 7 + tr - tf;
     let anti_diag_index: usize = tr + tf;
 
-    BitBoard(
-        hyp_quint(sq, occ, DIAG[diag_index].0).0
-            | hyp_quint(sq, occ, ANTI_DIAG[anti_diag_index].0).0,
-    )
+    hyp_quint(sq, occ, DIAG[diag_index].0) | hyp_quint(sq, occ, ANTI_DIAG[anti_diag_index].0)
 }
 
-// lookup bishop attacks for all bits (bitboard containing bishop occupancies)
-pub fn bishop_bb(board: BitBoard, occ: BitBoard) -> BitBoard {
-    let mut bb = BitBoard::empty();
-
-    for rank in 0..8 {
-        for file in 0..8 {
-            let square = rank * 8 + file;
-
-            let tr = square as usize / 8;
-            let tf = square as usize % 8;
-
-            let diag_index: usize = 7 + tr - tf;
-            let anti_diag_index: usize = tr + tf;
-
-            if board.0 & (1 << square) != 0 {
-                bb.0 |= hyp_quint(match_u32_to_sq(square), occ, DIAG[diag_index].0).0;
-                bb.0 |= hyp_quint(match_u32_to_sq(square), occ, ANTI_DIAG[anti_diag_index].0).0;
-            }
+// lookup bishop attacks for a bishop on a particular square
+pub fn bishop(sq: Square, occ: BitBoard) -> BitBoard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    {
+        if cfg!(target_feature = "bmi2") {
+            return unsafe { pext::bishop(sq, occ) };
         }
+        unsafe { (backend().bishop)(sq, occ) }
     }
-    bb
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+    magic::bishop(sq, occ)
+}
+
+// lookup bishop attacks for all bits (bitboard containing bishop occupancies)
+pub fn bishop_bb(board: BitBoard, occ: BitBoard) -> BitBoard {
+    board.into_iter().fold(BitBoard::empty(), |bb, sq| bb | bishop(sq, occ))
 }
 
 // lookup queen attacks for a queen on a particular square
 pub fn queen(sq: Square, occ: BitBoard) -> BitBoard {
-    BitBoard(rook(sq, occ).0 | bishop(sq, occ).0)
+    rook(sq, occ) | bishop(sq, occ)
 }
 
 // lookup queen attacks for all bits (bitboard containing queen occupancies)
 pub fn queen_bb(board: BitBoard, occ: BitBoard) -> BitBoard {
-    let mut bb = BitBoard::empty();
+    rook_bb(board, occ) | bishop_bb(board, occ)
+}
 
-    for rank in 0..8 {
-        for file in 0..8 {
-            let square = rank * 8 + file;
+// pieces of both colours attacking a square, sliders see through the given occupancy
+pub fn attackers_to(sq: Square, pieces: &[[BitBoard; 6]; 2], occ: BitBoard) -> BitBoard {
+    let [black, white] = pieces;
+    let both = |kind: Kind| black[kind as usize] | white[kind as usize];
+    let diagonal = both(Kind::Bishop) | both(Kind::Queen);
+    let straight = both(Kind::Rook) | both(Kind::Queen);
+
+    (pawn::lookup(sq, Colour::White) & black[Kind::Pawn as usize])
+        | (pawn::lookup(sq, Colour::Black) & white[Kind::Pawn as usize])
+        | (knight::lookup(sq) & both(Kind::Knight))
+        | (king::lookup(sq) & both(Kind::King))
+        | (bishop(sq, occ) & diagonal)
+        | (rook(sq, occ) & straight)
+}
 
-            if board.0 & (1 << square) != 0 {
-                bb.0 |= rook_bb(BitBoard::from_sq(match_u32_to_sq(square)), occ).0;
-                bb.0 |= bishop_bb(BitBoard::from_sq(match_u32_to_sq(square)), occ).0;
-            }
-        }
-    }
-    bb
+// check if a square is attacked by any piece of the given side
+pub fn is_attacked_by(
+    sq: Square,
+    side: Colour,
+    pieces: &[[BitBoard; 6]; 2],
+    occ: BitBoard,
+) -> bool {
+    let attackers = &pieces[side as usize];
+    let diagonal = attackers[Kind::Bishop as usize] | attackers[Kind::Queen as usize];
+    let straight = attackers[Kind::Rook as usize] | attackers[Kind::Queen as usize];
+
+    // a pawn of the given side attacks the square if a pawn of the other side on it would attack it
+    !(pawn::lookup(sq, side.opposite()) & attackers[Kind::Pawn as usize]).is_empty()
+        || !(knight::lookup(sq) & attackers[Kind::Knight as usize]).is_empty()
+        || !(king::lookup(sq) & attackers[Kind::King as usize]).is_empty()
+        || !(bishop(sq, occ) & diagonal).is_empty()
+        || !(rook(sq, occ) & straight).is_empty()
 }
@@ -0,0 +1,143 @@
+use crate::{File, Kind, Square};
+use std::fmt;
+use std::ops::Index;
+
+// maximum number of moves in any legal chess position is 218
+pub const MAX_MOVES: usize = 256;
+
+// special move types
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveFlag {
+    Normal,
+    DoublePush,
+    EnPassant,
+    Castle,
+}
+
+// a single move, eg. white pawn e7 takes d8 and promotes to a queen
+// [from: E7, to: D8, piece: Pawn, capture: Some(Rook), promotion: Some(Queen)]
+// castling is stored as the king capturing its own rook, which also covers Chess960
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub piece: Kind,
+    pub capture: Option<Kind>,
+    pub promotion: Option<Kind>,
+    pub flag: MoveFlag,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square, piece: Kind, capture: Option<Kind>) -> Move {
+        Move { from, to, piece, capture, promotion: None, flag: MoveFlag::Normal }
+    }
+    // check if the move captures a piece (including en passant)
+    pub fn is_capture(&self) -> bool {
+        self.capture.is_some()
+    }
+    // check if the move is a capture or a promotion
+    pub fn is_tactical(&self) -> bool {
+        self.capture.is_some() || self.promotion.is_some()
+    }
+    // long algebraic (UCI) notation, castling is written as the king's move (e1g1) unless
+    // UCI_Chess960 is set, then as the king taking its rook (e1h1)
+    pub fn to_uci(&self, chess960: bool) -> String {
+        let to = match self.flag {
+            MoveFlag::Castle if !chess960 => castling_squares(self.from, self.to).0,
+            _ => self.to,
+        };
+
+        let mut uci = format!("{}{}", self.from, to);
+        if let Some(kind) = self.promotion {
+            uci.push(kind.to_char());
+        }
+        uci
+    }
+}
+
+// king and rook [to] squares of a castling move, the same files (g and f, or c and d) wherever
+// the pieces started
+pub fn castling_squares(king_from: Square, rook_from: Square) -> (Square, Square) {
+    let rank = king_from.rank();
+    let (king, rook) =
+        if rook_from.file() > king_from.file() { (File::G, File::F) } else { (File::C, File::D) };
+
+    (Square::new(king, rank), Square::new(rook, rank))
+}
+
+// UCI notation for standard chess, eg. e2e4 or e7e8q
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uci(false))
+    }
+}
+
+const EMPTY_MOVE: Move = Move {
+    from: Square::A8,
+    to: Square::A8,
+    piece: Kind::Pawn,
+    capture: None,
+    promotion: None,
+    flag: MoveFlag::Normal,
+};
+
+// fixed size move list, avoids allocating during move generation
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    count: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList { moves: [EMPTY_MOVE; MAX_MOVES], count: 0 }
+    }
+    pub fn push(&mut self, mv: Move) {
+        self.moves[self.count] = mv;
+        self.count += 1;
+    }
+    pub fn len(&self) -> usize {
+        self.count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.count]
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.as_slice().iter()
+    }
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.as_slice().contains(mv)
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Index<usize> for MoveList {
+    type Output = Move;
+
+    fn index(&self, index: usize) -> &Move {
+        &self.as_slice()[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -4,34 +4,47 @@ use std::process::exit;
 
 pub fn east(board: BitBoard, colour: Colour) -> BitBoard {
     match colour {
-        Colour::White => BitBoard((board.0 >> 9) & NOT_H_FILE.0),
-        Colour::Black => BitBoard((board.0 << 9) & NOT_A_FILE.0),
+        Colour::White => (board >> 9) & NOT_H_FILE,
+        Colour::Black => (board << 9) & NOT_A_FILE,
         Colour::Undefined => exit(1),
     }
 }
 
 pub fn west(board: BitBoard, colour: Colour) -> BitBoard {
     match colour {
-        Colour::White => BitBoard((board.0 >> 7) & NOT_A_FILE.0),
-        Colour::Black => BitBoard((board.0 << 7) & NOT_H_FILE.0),
+        Colour::White => (board >> 7) & NOT_A_FILE,
+        Colour::Black => (board << 7) & NOT_H_FILE,
         Colour::Undefined => exit(1),
     }
 }
 
-pub fn lookup(square: Square, colour: Colour) -> BitBoard {
-    let board = BitBoard::from_sq(square);
+// pawn attacks for every square, indexed by [colour][square] and generated at compile time
+pub static PAWN_ATTACKS: [[BitBoard; 64]; 2] = {
+    let mut table = [[EMPTY; 64]; 2];
+    let mut sq = 0;
+
+    while sq < 64 {
+        let board: u64 = 1 << sq;
+        table[Colour::Black as usize][sq] =
+            BitBoard(((board << 9) & NOT_A_FILE.0) | ((board << 7) & NOT_H_FILE.0));
+        table[Colour::White as usize][sq] =
+            BitBoard(((board >> 9) & NOT_H_FILE.0) | ((board >> 7) & NOT_A_FILE.0));
+        sq += 1;
+    }
+    table
+};
 
+pub fn lookup(square: Square, colour: Colour) -> BitBoard {
     match colour {
-        Colour::White => BitBoard(east(board, Colour::White).0 | west(board, Colour::White).0),
-        Colour::Black => BitBoard(east(board, Colour::Black).0 | west(board, Colour::Black).0),
         Colour::Undefined => exit(1),
+        _ => PAWN_ATTACKS[colour as usize][square as usize],
     }
 }
 
 pub fn all(board: BitBoard, colour: Colour) -> BitBoard {
     match colour {
-        Colour::White => BitBoard(east(board, Colour::White).0 | west(board, Colour::White).0),
-        Colour::Black => BitBoard(east(board, Colour::Black).0 | west(board, Colour::Black).0),
+        Colour::White => east(board, Colour::White) | west(board, Colour::White),
+        Colour::Black => east(board, Colour::Black) | west(board, Colour::Black),
         Colour::Undefined => exit(1),
     }
 }
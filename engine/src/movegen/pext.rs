@@ -0,0 +1,122 @@
+use crate::bitboard::*;
+use crate::movegen::magic::{self, Slider};
+use crate::movegen::{bishop_hq, rook_hq};
+use crate::Square;
+use std::arch::x86_64::_pext_u64;
+use std::sync::OnceLock;
+
+// pext lookup for one square: index = offset + pext(occ, mask)
+#[derive(Copy, Clone, Debug, Default)]
+struct Entry {
+    mask: u64,
+    offset: usize,
+}
+
+struct Tables {
+    rook: [Entry; 64],
+    bishop: [Entry; 64],
+    attacks: Vec<u64>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+// check if the cpu supports BMI2, known at compile time with `-C target-feature=+bmi2` and
+// otherwise detected once and cached by std
+pub fn available() -> bool {
+    cfg!(target_feature = "bmi2") || is_x86_feature_detected!("bmi2")
+}
+
+#[target_feature(enable = "bmi2")]
+#[inline]
+unsafe fn index(entry: &Entry, occ: BitBoard) -> usize {
+    entry.offset + _pext_u64(occ.0, entry.mask) as usize
+}
+
+// the n-th subset of the mask, the inverse of pext (a software pdep)
+fn deposit(mut n: u64, mut mask: u64) -> u64 {
+    let mut result = 0;
+
+    while mask != 0 {
+        let bit = mask & mask.wrapping_neg();
+        if n & 1 != 0 {
+            result |= bit;
+        }
+        n >>= 1;
+        mask &= mask - 1;
+    }
+    result
+}
+
+fn build(slider: Slider, attacks: &mut Vec<u64>) -> [Entry; 64] {
+    let mut table = [Entry::default(); 64];
+
+    for (i, entry) in table.iter_mut().enumerate() {
+        let sq = Square::ALL[i];
+        let mask = magic::mask(sq, slider);
+
+        *entry = Entry { mask, offset: attacks.len() };
+
+        // pext(deposit(n, mask), mask) == n, so subsets can be stored in order
+        for n in 0..1u64 << mask.count_ones() {
+            let occ = BitBoard(deposit(n, mask));
+            attacks.push(match slider {
+                Slider::Rook => rook_hq(sq, occ).0,
+                Slider::Bishop => bishop_hq(sq, occ).0,
+            });
+        }
+    }
+    table
+}
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let mut attacks = Vec::new();
+        let rook = build(Slider::Rook, &mut attacks);
+        let bishop = build(Slider::Bishop, &mut attacks);
+
+        Tables { rook, bishop, attacks }
+    })
+}
+
+/// # Safety
+/// The cpu must support BMI2, see `available`.
+#[target_feature(enable = "bmi2")]
+pub unsafe fn rook(sq: Square, occ: BitBoard) -> BitBoard {
+    let tables = tables();
+    BitBoard(tables.attacks[unsafe { index(&tables.rook[sq as usize], occ) }])
+}
+
+/// # Safety
+/// The cpu must support BMI2, see `available`.
+#[target_feature(enable = "bmi2")]
+pub unsafe fn bishop(sq: Square, occ: BitBoard) -> BitBoard {
+    let tables = tables();
+    BitBoard(tables.attacks[unsafe { index(&tables.bishop[sq as usize], occ) }])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movegen::pext::*;
+
+    #[test]
+    fn check_pext_against_magic() {
+        if !available() {
+            return;
+        }
+
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        for _ in 0..2000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let occ = BitBoard(state & state.rotate_left(17));
+
+            for sq in Square::ALL {
+                unsafe {
+                    assert_eq!(rook(sq, occ), magic::rook(sq, occ), "rook on {:?}", sq);
+                    assert_eq!(bishop(sq, occ), magic::bishop(sq, occ), "bishop on {:?}", sq);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::bitboard::*;
+use crate::Square;
+
+// (file, rank) steps of the four line orientations: rank, file, diagonal and anti-diagonal
+const ORIENTATIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+const fn on_board(file: i32, rank: i32) -> bool {
+    file >= 0 && file < 8 && rank >= 0 && rank < 8
+}
+
+// squares reached from a square walking in one direction until the edge (excluding the square)
+const fn ray(sq: usize, df: i32, dr: i32) -> u64 {
+    let mut bb = 0;
+    let mut file = (sq % 8) as i32 + df;
+    let mut rank = (sq / 8) as i32 + dr;
+
+    while on_board(file, rank) {
+        bb |= 1 << (rank * 8 + file);
+        file += df;
+        rank += dr;
+    }
+    bb
+}
+
+// squares strictly between two squares sharing a rank, file or diagonal, empty otherwise
+pub static BETWEEN: [[BitBoard; 64]; 64] = {
+    let mut table = [[EMPTY; 64]; 64];
+    let mut a = 0;
+
+    while a < 64 {
+        let mut i = 0;
+        while i < 8 {
+            // both directions of every orientation
+            let (df, dr) = ORIENTATIONS[i % 4];
+            let (df, dr) = if i < 4 { (df, dr) } else { (-df, -dr) };
+
+            let mut between = 0;
+            let mut file = (a % 8) as i32 + df;
+            let mut rank = (a / 8) as i32 + dr;
+
+            while on_board(file, rank) {
+                let b = (rank * 8 + file) as usize;
+                table[a][b] = BitBoard(between);
+                between |= 1 << b;
+                file += df;
+                rank += dr;
+            }
+            i += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+// the whole line (edge to edge) through two aligned squares, empty otherwise
+pub static LINE: [[BitBoard; 64]; 64] = {
+    let mut table = [[EMPTY; 64]; 64];
+    let mut a = 0;
+
+    while a < 64 {
+        let mut i = 0;
+        while i < 4 {
+            let (df, dr) = ORIENTATIONS[i];
+            let line = ray(a, df, dr) | ray(a, -df, -dr) | 1 << a;
+
+            let mut others = line & !(1 << a);
+            while others != 0 {
+                table[a][others.trailing_zeros() as usize] = BitBoard(line);
+                others &= others - 1;
+            }
+            i += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+pub fn between(a: Square, b: Square) -> BitBoard {
+    BETWEEN[a as usize][b as usize]
+}
+
+pub fn line(a: Square, b: Square) -> BitBoard {
+    LINE[a as usize][b as usize]
+}
+
+// check if three squares lie on one rank, file or diagonal
+pub fn aligned(a: Square, b: Square, c: Square) -> bool {
+    LINE[a as usize][b as usize].get_bit(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movegen::rays::*;
+
+    #[test]
+    fn check_between_and_line() {
+        let mut expected = BitBoard::empty();
+        expected.set_bit(Square::D5);
+        expected.set_bit(Square::E6);
+        expected.set_bit(Square::F7);
+        assert_eq!(between(Square::C4, Square::G8), expected);
+        assert_eq!(between(Square::G8, Square::C4), expected);
+
+        assert!(between(Square::A1, Square::B3).is_empty());
+        assert!(between(Square::E4, Square::E5).is_empty());
+        assert!(line(Square::A1, Square::B3).is_empty());
+
+        assert_eq!(line(Square::E2, Square::E7), FILES[4]);
+        assert_eq!(line(Square::A1, Square::H8), ANTI_DIAG[7]);
+        assert!(aligned(Square::A8, Square::D5, Square::H1));
+        assert!(!aligned(Square::A8, Square::D5, Square::H2));
+    }
+}
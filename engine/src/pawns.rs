@@ -0,0 +1,225 @@
+use crate::bitboard::*;
+use crate::movegen::{king, pawn};
+use crate::position::Position;
+use crate::{Colour, Kind};
+
+// set-wise fills and spans (https://www.chessprogramming.org/Pawn_Fills), north is towards the
+// 8th rank (lower square indices). Fills include the squares they start from, spans do not
+
+// every square on and in front of the set bits, from white's side, in three shifts
+pub fn north_fill(board: BitBoard) -> BitBoard {
+    let mut fill = board;
+    fill |= fill >> 8;
+    fill |= fill >> 16;
+    fill | (fill >> 32)
+}
+
+// every square on and behind the set bits, from white's side
+pub fn south_fill(board: BitBoard) -> BitBoard {
+    let mut fill = board;
+    fill |= fill << 8;
+    fill |= fill << 16;
+    fill | (fill << 32)
+}
+
+// the whole file of every set bit
+pub fn file_fill(board: BitBoard) -> BitBoard {
+    north_fill(board) | south_fill(board)
+}
+
+// fill towards the side's last rank
+pub fn front_fill(board: BitBoard, colour: Colour) -> BitBoard {
+    match colour {
+        Colour::White => north_fill(board),
+        _ => south_fill(board),
+    }
+}
+
+// fill towards the side's first rank
+pub fn rear_fill(board: BitBoard, colour: Colour) -> BitBoard {
+    front_fill(board, colour.opposite())
+}
+
+// squares the pawns still have to pass on their way to promotion
+pub fn front_span(pawns: BitBoard, colour: Colour) -> BitBoard {
+    match colour {
+        Colour::White => king::no(north_fill(pawns)),
+        _ => king::so(south_fill(pawns)),
+    }
+}
+
+// squares behind the pawns on their files
+pub fn rear_span(pawns: BitBoard, colour: Colour) -> BitBoard {
+    front_span(pawns, colour.opposite())
+}
+
+// squares the pawns attack now or may attack after advancing
+pub fn attack_front_span(pawns: BitBoard, colour: Colour) -> BitBoard {
+    let span = front_span(pawns, colour);
+    king::ea(span) | king::we(span)
+}
+
+// squares on the neighbouring files level with or behind the pawns, where pawns could defend
+// them from
+pub fn attack_rear_span(pawns: BitBoard, colour: Colour) -> BitBoard {
+    let fill = rear_fill(pawns, colour);
+    king::ea(fill) | king::we(fill)
+}
+
+// own and enemy pawns of a side
+fn pawns(position: &Position, colour: Colour) -> (BitBoard, BitBoard) {
+    let ours = position.pieces_of(colour, Kind::Pawn);
+    let theirs = position.pieces_of(colour.opposite(), Kind::Pawn);
+    (ours, theirs)
+}
+
+// pawns with no enemy pawn in front of them on their own or a neighbouring file
+pub fn passed_pawns(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, theirs) = pawns(position, colour);
+    let them = colour.opposite();
+    ours & !(front_span(theirs, them) | attack_front_span(theirs, them))
+}
+
+// pawns without own pawns on the neighbouring files
+pub fn isolated_pawns(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, _) = pawns(position, colour);
+    ours & !file_fill(king::ea(ours) | king::we(ours))
+}
+
+// pawns with another own pawn in front of them on the same file, the frontmost pawn of the file
+// is not included
+pub fn doubled_pawns(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, _) = pawns(position, colour);
+    ours & rear_span(ours, colour)
+}
+
+// pawns that cannot advance safely because the square in front is attacked by an enemy pawn, and
+// that no own pawn can ever defend it
+pub fn backward_pawns(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, theirs) = pawns(position, colour);
+    let stops = match colour {
+        Colour::White => king::no(ours),
+        _ => king::so(ours),
+    };
+    let unsafe_stops =
+        stops & pawn::all(theirs, colour.opposite()) & !attack_front_span(ours, colour);
+
+    match colour {
+        Colour::White => ours & king::so(unsafe_stops),
+        _ => ours & king::no(unsafe_stops),
+    }
+}
+
+// pawns that are not passed but have no enemy pawn in front of them on their file, and at least
+// as many own pawns level or behind on the neighbouring files (helpers) as there are enemy pawns
+// guarding the squares in front (sentries)
+pub fn candidate_pawns(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, theirs) = pawns(position, colour);
+    let passed = passed_pawns(position, colour);
+
+    (ours & !passed)
+        .into_iter()
+        .filter(|sq| {
+            let pawn = BitBoard::from_sq(*sq);
+            let front = front_span(pawn, colour);
+            if !(front & theirs).is_empty() {
+                return false;
+            }
+
+            let sentries = theirs & pawn::all(front, colour);
+            let helpers = ours & attack_rear_span(pawn, colour);
+            helpers.count_bits() >= sentries.count_bits()
+        })
+        .collect()
+}
+
+// files without any pawns
+pub fn open_files(position: &Position) -> BitBoard {
+    let (white, black) = pawns(position, Colour::White);
+    !file_fill(white | black)
+}
+
+// files without pawns of the given side but with enemy pawns
+pub fn half_open_files(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, theirs) = pawns(position, colour);
+    !file_fill(ours) & file_fill(theirs)
+}
+
+// squares on the 4th to 6th rank (from the side's point of view) defended by an own pawn that no
+// enemy pawn can ever attack
+pub fn outposts(position: &Position, colour: Colour) -> BitBoard {
+    let (ours, theirs) = pawns(position, colour);
+    let ranks = match colour {
+        Colour::White => RANKS[2] | RANKS[3] | RANKS[4],
+        _ => RANKS[3] | RANKS[4] | RANKS[5],
+    };
+    let them = colour.opposite();
+    ranks & pawn::all(ours, colour) & !attack_front_span(theirs, them)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pawns::*;
+    use crate::Square;
+
+    fn squares(squares: &[Square]) -> BitBoard {
+        squares.iter().copied().collect()
+    }
+
+    #[test]
+    fn check_fills_and_spans() {
+        let e4 = BitBoard::from_sq(Square::E4);
+
+        assert_eq!(
+            north_fill(e4),
+            squares(&[Square::E4, Square::E5, Square::E6, Square::E7, Square::E8])
+        );
+        assert_eq!(file_fill(e4), FILES[4]);
+        assert_eq!(front_span(e4, Colour::Black), squares(&[Square::E3, Square::E2, Square::E1]));
+        assert_eq!(
+            rear_span(e4, Colour::Black),
+            squares(&[Square::E5, Square::E6, Square::E7, Square::E8])
+        );
+        assert_eq!(
+            attack_front_span(e4, Colour::White),
+            front_span(
+                BitBoard::from_sq(Square::D4) | BitBoard::from_sq(Square::F4),
+                Colour::White
+            )
+        );
+        assert_eq!(
+            attack_rear_span(BitBoard::from_sq(Square::A2), Colour::White),
+            squares(&[Square::B2, Square::B1])
+        );
+    }
+
+    #[test]
+    fn check_pawn_structure() {
+        let position = Position::from_fen("4k3/8/3p4/8/4P3/8/P7/4K3 w - - 0 1").unwrap();
+        assert_eq!(passed_pawns(&position, Colour::White), squares(&[Square::A2]));
+        assert_eq!(passed_pawns(&position, Colour::Black), EMPTY);
+
+        let position = Position::from_fen("4k3/8/8/8/4P3/4P3/PP2P2P/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            isolated_pawns(&position, Colour::White),
+            squares(&[Square::E4, Square::E3, Square::E2, Square::H2])
+        );
+        assert_eq!(doubled_pawns(&position, Colour::White), squares(&[Square::E3, Square::E2]));
+
+        let position = Position::from_fen("4k3/8/8/5p2/3P4/4P3/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(backward_pawns(&position, Colour::White), squares(&[Square::E3]));
+        assert_eq!(backward_pawns(&position, Colour::Black), squares(&[Square::F5]));
+
+        // c4 has a helper on b4 for the sentry on b6, b4 is blocked on its file
+        let position = Position::from_fen("4k3/8/1p6/8/1PP5/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(candidate_pawns(&position, Colour::White), squares(&[Square::C4]));
+        assert_eq!(candidate_pawns(&position, Colour::Black), EMPTY);
+        assert_eq!(open_files(&position), !(FILES[1] | FILES[2]));
+        assert_eq!(half_open_files(&position, Colour::White), EMPTY);
+        assert_eq!(half_open_files(&position, Colour::Black), FILES[2]);
+        assert_eq!(outposts(&position, Colour::White), squares(&[Square::B5, Square::D5]));
+
+        let position = Position::from_fen("4k3/8/1p6/8/2P5/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(candidate_pawns(&position, Colour::White), EMPTY);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::fen;
+use crate::movegen::*;
+use crate::position::Position;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+
+// reference position with known node counts, counts[0] is depth 1
+pub struct PerftCase {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub counts: &'static [u64],
+}
+
+// result of checking one suite position at one depth
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuiteResult {
+    pub name: &'static str,
+    pub depth: u32,
+    pub expected: u64,
+    pub nodes: u64,
+}
+
+impl SuiteResult {
+    pub fn is_ok(&self) -> bool {
+        self.expected == self.nodes
+    }
+}
+
+// https://www.chessprogramming.org/Perft_Results
+pub const SUITE: [PerftCase; 9] = [
+    PerftCase {
+        name: "start",
+        fen: fen::START_POS,
+        counts: &[20, 400, 8902, 197281, 4865609, 119060324, 3195901860],
+    },
+    PerftCase {
+        name: "tricky",
+        fen: fen::TRICKY_POS,
+        counts: &[48, 2039, 97862, 4085603, 193690690, 8031647685],
+    },
+    PerftCase {
+        name: "killer",
+        fen: fen::KILLER_POS,
+        counts: &[42, 1088, 39518, 1032012, 36112837],
+    },
+    PerftCase { name: "cmk", fen: fen::CMK_POS, counts: &[43, 1289, 54240, 1679340, 69838845] },
+    PerftCase {
+        name: "endgame",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        counts: &[14, 191, 2812, 43238, 674624, 11030083, 178633661],
+    },
+    PerftCase {
+        name: "promotions",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        counts: &[6, 264, 9467, 422333, 15833292, 706045033],
+    },
+    PerftCase {
+        name: "discovered",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        counts: &[44, 1486, 62379, 2103487, 89941194],
+    },
+    PerftCase {
+        name: "chess960",
+        fen: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        counts: &[21, 528, 12189, 326672, 8146062],
+    },
+    // the king and the kingside rook start on each other's destination squares
+    PerftCase {
+        name: "chess960 overlap",
+        fen: "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        counts: &[20, 479, 10471, 273318, 6417013],
+    },
+];
+
+// count all leaf nodes of the move tree at the given depth
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for mv in &generate_legal_moves(position) {
+        position.make_move(*mv);
+        nodes += perft(position, depth - 1);
+        position.unmake_move(*mv);
+    }
+    nodes
+}
+
+// perft that counts the legal moves at depth 1 instead of playing them
+pub fn perft_bulk(position: &mut Position, depth: u32) -> u64 {
+    perft_hashed(position, depth, None)
+}
+
+fn perft_hashed(position: &mut Position, depth: u32, table: Option<&PerftTable>) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    // hits skip move generation, depth 1 is cheaper to count than to look up
+    let key = position.hash;
+    if depth > 1 {
+        if let Some(nodes) = table.and_then(|table| table.probe(key, depth)) {
+            return nodes;
+        }
+    }
+
+    let moves = generate_legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in &moves {
+        position.make_move(*mv);
+        nodes += perft_hashed(position, depth - 1, table);
+        position.unmake_move(*mv);
+    }
+
+    if let Some(table) = table {
+        table.store(key, depth, nodes);
+    }
+    nodes
+}
+
+// bulk counting perft with the root moves shared between worker threads, and subtree counts
+// optionally cached in a table shared by all of them
+pub fn perft_parallel(
+    position: &Position,
+    depth: u32,
+    threads: usize,
+    table: Option<&PerftTable>,
+) -> u64 {
+    if depth <= 1 {
+        return perft_bulk(&mut position.clone(), depth);
+    }
+
+    let moves = generate_legal_moves(position);
+    let next = AtomicUsize::new(0);
+    let nodes = AtomicU64::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut position = position.clone();
+
+                // each worker takes the next unclaimed root move until none are left
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= moves.len() {
+                        break;
+                    }
+
+                    position.make_move(moves[i]);
+                    let count = perft_hashed(&mut position, depth - 1, table);
+                    position.unmake_move(moves[i]);
+
+                    nodes.fetch_add(count, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    nodes.into_inner()
+}
+
+// lockless transposition table for perft counts, each entry stores the key xored with its data
+// so that an entry torn by two threads writing at once is detected and ignored
+pub struct PerftTable {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl PerftTable {
+    pub fn new(size_mb: usize) -> PerftTable {
+        let count = (size_mb * 1024 * 1024 / 16).max(1);
+        let entries = (0..count).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect();
+
+        PerftTable { entries }
+    }
+
+    fn probe(&self, key: u64, depth: u32) -> Option<u64> {
+        let (check, data) = &self.entries[key as usize % self.entries.len()];
+        let data = data.load(Ordering::Relaxed);
+
+        // low byte holds the depth, the rest the node count
+        if check.load(Ordering::Relaxed) ^ data == key && data & 0xff == depth as u64 {
+            Some(data >> 8)
+        } else {
+            None
+        }
+    }
+
+    fn store(&self, key: u64, depth: u32, nodes: u64) {
+        let (check, data) = &self.entries[key as usize % self.entries.len()];
+        let packed = nodes << 8 | depth as u64;
+
+        check.store(key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+}
+
+// perft split by root move, useful to find the move where two generators disagree. Every
+// subtree is counted like perft_parallel, with the same threads and table
+pub fn divide(
+    position: &Position,
+    depth: u32,
+    threads: usize,
+    table: Option<&PerftTable>,
+) -> Vec<(Move, u64)> {
+    let mut counts = Vec::new();
+
+    if depth == 0 {
+        return counts;
+    }
+
+    let mut position = position.clone();
+    for mv in &generate_legal_moves(&position) {
+        position.make_move(*mv);
+        counts.push((*mv, perft_parallel(&position, depth - 1, threads, table)));
+        position.unmake_move(*mv);
+    }
+    counts
+}
+
+// run every suite position up to max_depth (or as deep as its known counts go), reporting each
+// result as it finishes, and return the mismatches
+pub fn run_suite(max_depth: u32, mut report: impl FnMut(&SuiteResult)) -> Vec<SuiteResult> {
+    let mut mismatches = Vec::new();
+
+    for case in SUITE.iter() {
+        let mut position = Position::from_fen(case.fen).expect("suite positions are valid");
+
+        for (i, expected) in case.counts.iter().enumerate().take(max_depth as usize) {
+            let depth = i as u32 + 1;
+            let result = SuiteResult {
+                name: case.name,
+                depth,
+                expected: *expected,
+                nodes: perft_bulk(&mut position, depth),
+            };
+
+            report(&result);
+            if !result.is_ok() {
+                mismatches.push(result);
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::perft::*;
+
+    #[test]
+    fn check_suite() {
+        assert!(run_suite(3, |_| {}).is_empty());
+    }
+
+    #[test]
+    fn check_parallel_hashed() {
+        let position = Position::from_fen(fen::TRICKY_POS).unwrap();
+        let table = PerftTable::new(1);
+
+        assert_eq!(perft_parallel(&position, 3, 2, None), 97862);
+        assert_eq!(perft_parallel(&position, 3, 2, Some(&table)), 97862);
+        assert_eq!(perft_parallel(&position, 3, 2, Some(&table)), 97862);
+    }
+
+    #[test]
+    fn check_divide() {
+        let position = Position::from_fen(fen::START_POS).unwrap();
+        let counts = divide(&position, 3, 1, None);
+
+        assert_eq!(counts.len(), 20);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+
+        let table = PerftTable::new(1);
+        assert_eq!(divide(&position, 4, 2, Some(&table)), divide(&position, 4, 1, None));
+    }
+}
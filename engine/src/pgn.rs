@@ -0,0 +1,871 @@
+use crate::fen::FenError;
+use crate::movegen::Move;
+use crate::position::Position;
+use crate::san::{self, Notation, SanError};
+use crate::validate::Problem;
+use crate::Colour;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::mem;
+use std::time::Duration;
+
+// tags every exported game has, in this order
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    // game still in progress, abandoned or result unknown
+    Ongoing,
+}
+
+impl GameResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        }
+    }
+
+    pub fn from_token(token: &str) -> Option<GameResult> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Ongoing),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// engine evaluation from a [%eval] comment, from white's point of view
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Eval {
+    Centipawns(i32),
+    // mate in n moves, negative when black mates
+    Mate(i32),
+}
+
+// a move of the game tree with its annotations
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnNode {
+    pub mv: Move,
+    // comment in front of the move, only kept for the first move of a line
+    pub starting_comment: Option<String>,
+    // comment after the move, without the [%clk] and [%eval] commands
+    pub comment: Option<String>,
+    // numeric annotation glyphs, eg. 1 for '!' and 4 for '??'
+    pub nags: Vec<u8>,
+    // clock time left after the move
+    pub clock: Option<Duration>,
+    pub eval: Option<Eval>,
+    // alternatives to this move, every line starts from the position before it
+    pub variations: Vec<Vec<PgnNode>>,
+}
+
+impl PgnNode {
+    pub fn new(mv: Move) -> PgnNode {
+        PgnNode {
+            mv,
+            starting_comment: None,
+            comment: None,
+            nags: Vec::new(),
+            clock: None,
+            eval: None,
+            variations: Vec::new(),
+        }
+    }
+
+    // take the known commands out of a comment and keep the rest of the text
+    fn add_comment(&mut self, text: &str) {
+        let mut rest = String::new();
+        let mut text = text;
+
+        while let Some(start) = text.find("[%") {
+            let end = match text[start..].find(']') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let command = text[start + 2..end].trim();
+            let (name, value) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+
+            let parsed = match name {
+                "clk" => parse_clock(value.trim()).map(|clock| self.clock = Some(clock)),
+                "eval" => parse_eval(value.trim()).map(|eval| self.eval = Some(eval)),
+                _ => None,
+            };
+            rest.push_str(&text[..start]);
+            if parsed.is_none() {
+                rest.push_str(&text[start..=end]);
+            }
+            text = &text[end + 1..];
+        }
+        rest.push_str(text);
+
+        let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !rest.is_empty() {
+            self.comment = Some(match self.comment.take() {
+                Some(comment) => format!("{} {}", comment, rest),
+                None => rest,
+            });
+        }
+    }
+}
+
+// h:mm:ss with optional fractions of a second
+fn parse_clock(value: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok().filter(|part| *part >= 0.0)?;
+    }
+    Some(Duration::from_millis((seconds * 1000.0).round() as u64))
+}
+
+// pawns (0.34, -1.5) or mate (#3, #-2), optionally followed by the search depth (0.34,20)
+fn parse_eval(value: &str) -> Option<Eval> {
+    let value = value.split(',').next()?;
+    match value.strip_prefix('#') {
+        Some(mate) => mate.parse().ok().map(Eval::Mate),
+        None => {
+            value.parse::<f64>().ok().map(|pawns| Eval::Centipawns((pawns * 100.0).round() as i32))
+        }
+    }
+}
+
+// [%clk h:mm:ss] with fractions of a second only when there are some
+fn format_clock(clock: Duration) -> String {
+    let millis = clock.as_millis();
+    let seconds = millis / 1000;
+    let mut clock = format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if !millis.is_multiple_of(1000) {
+        clock.push_str(format!(".{:03}", millis % 1000).trim_end_matches('0'));
+    }
+    clock
+}
+
+fn format_eval(eval: Eval) -> String {
+    match eval {
+        Eval::Centipawns(centipawns) => format!("{:.2}", centipawns as f64 / 100.0),
+        Eval::Mate(moves) => format!("#{}", moves),
+    }
+}
+
+// a parsed and replayed game
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    // tag pairs in the order they were read
+    pub tags: Vec<(String, String)>,
+    // position before the first move, from the FEN tag if there is one
+    pub start: Position,
+    pub moves: Vec<PgnNode>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    // game without tags or moves, eg. to record a game as it is played
+    pub fn new(start: Position) -> PgnGame {
+        PgnGame { tags: Vec::new(), start, moves: Vec::new(), result: GameResult::Ongoing }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // replace the value of a tag, or add it at the end
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|node| node.mv)
+    }
+
+    // position after the last move of the main line
+    pub fn end_position(&self) -> Position {
+        let mut position = self.start.clone();
+        for mv in self.mainline() {
+            position.make_move(mv);
+        }
+        position
+    }
+
+    // export format: the seven tag roster first ("?" when missing), SetUp and FEN for games
+    // that do not start from the standard position, English SAN and lines of at most 80 columns
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let mut tag = |name: &str, value: &str| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        };
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            tag(name, value);
+        }
+
+        let fen = self.start.to_fen();
+        if fen != crate::fen::START_POS.trim() || self.start.chess960 {
+            if self.start.chess960 && self.tag("Variant").is_none() {
+                tag("Variant", "Chess960");
+            }
+            tag("SetUp", "1");
+            tag("FEN", &fen);
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                tag(name, value);
+            }
+        }
+
+        let mut tokens = Vec::new();
+        write_line(&mut self.start.clone(), &self.moves, &mut tokens);
+        tokens.push(self.result.to_string());
+
+        pgn.push('\n');
+        pgn.push_str(&wrap(&tokens.join(" "), 80));
+        pgn.push('\n');
+        pgn
+    }
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+// movetext of a line and its variations, a move number is written for every white move and for
+// black moves that follow a comment, a variation or start a line
+fn write_line(position: &mut Position, line: &[PgnNode], tokens: &mut Vec<String>) {
+    let mut numbered = false;
+
+    for node in line {
+        if let Some(comment) = &node.starting_comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        if position.side_to_move == Colour::White || !numbered {
+            tokens.push(move_number(position));
+        }
+        tokens.push(san::to_san(position, node.mv));
+        tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        numbered = true;
+
+        let mut comment = Vec::new();
+        if let Some(eval) = node.eval {
+            comment.push(format!("[%eval {}]", format_eval(eval)));
+        }
+        if let Some(clock) = node.clock {
+            comment.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        comment.extend(node.comment.clone());
+        if !comment.is_empty() {
+            tokens.push(format!("{{{}}}", comment.join(" ")));
+            numbered = false;
+        }
+
+        for variation in node.variations.iter().filter(|variation| !variation.is_empty()) {
+            let mut moves = Vec::new();
+            write_line(&mut position.clone(), variation, &mut moves);
+            moves[0].insert(0, '(');
+            moves.last_mut().expect("variation has moves").push(')');
+            tokens.append(&mut moves);
+            numbered = false;
+        }
+
+        position.make_move(node.mv);
+    }
+}
+
+// break text between words so that no line is longer than width, longer words get a line
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    Io(String),
+    // a tag pair that is not [Name "value"]
+    InvalidTag(String),
+    InvalidFen(FenError),
+    // a FEN tag position that cannot arise in a game, eg. without kings
+    IllegalPosition(Vec<Problem>),
+    // a move that does not parse or is not legal, with its move number (eg. 12...)
+    InvalidMove { number: String, error: SanError },
+    // a token in a place it cannot be, eg. ')' without '(' or a NAG before the first move
+    UnexpectedToken(String),
+    UnterminatedComment,
+    UnterminatedVariation,
+}
+
+// error in one game of a PGN file, counting games and lines from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    pub game: usize,
+    pub line: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "game {} (line {}): ", self.game, self.line)?;
+
+        match &self.kind {
+            PgnErrorKind::Io(err) => write!(f, "read error: {}", err),
+            PgnErrorKind::InvalidTag(tag) => write!(f, "invalid tag pair {}", tag),
+            PgnErrorKind::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            PgnErrorKind::IllegalPosition(problems) => {
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                write!(f, "illegal FEN tag position: {}", problems.join(", "))
+            }
+            PgnErrorKind::InvalidMove { number, error } => write!(f, "at {} {}", number, error),
+            PgnErrorKind::UnexpectedToken(token) => write!(f, "unexpected '{}'", token),
+            PgnErrorKind::UnterminatedComment => write!(f, "comment is never closed"),
+            PgnErrorKind::UnterminatedVariation => write!(f, "variation is never closed"),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    // SAN as written, with a NAG for trailing '!' and '?' glyphs
+    Move(String, Option<u8>),
+    Nag(u8),
+    Comment(String),
+    Open,
+    Close,
+    Result(GameResult),
+}
+
+// NAGs of the move suffix annotations
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnErrorKind> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if c.is_whitespace() || c == '.' => {}
+            '{' => match text[i + 1..].find('}') {
+                Some(end) => {
+                    tokens.push(Token::Comment(text[i + 1..i + 1 + end].to_string()));
+                    while chars.next_if(|(j, _)| *j <= i + 1 + end).is_some() {}
+                }
+                None => return Err(PgnErrorKind::UnterminatedComment),
+            },
+            ';' => {
+                let end = text[i..].find('\n').map_or(text.len(), |end| i + end);
+                tokens.push(Token::Comment(text[i + 1..end].to_string()));
+                while chars.next_if(|(j, _)| *j < end).is_some() {}
+            }
+            '[' => {
+                let end = text[i..].find(']').map_or(text.len(), |end| i + end);
+                tokens.push(tag_pair(&text[i..=end.min(text.len() - 1)])?);
+                while chars.next_if(|(j, _)| *j <= end).is_some() {}
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            _ => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| !is_delimiter(*c)) {
+                    end = j + c.len_utf8();
+                }
+                let symbol = &text[i..end];
+
+                if let Some(result) = GameResult::from_token(symbol) {
+                    tokens.push(Token::Result(result));
+                } else if let Some(nag) = symbol.strip_prefix('$') {
+                    let nag =
+                        nag.parse().map_err(|_| PgnErrorKind::UnexpectedToken(symbol.into()))?;
+                    tokens.push(Token::Nag(nag));
+                } else if !symbol.chars().all(|c| c.is_ascii_digit()) {
+                    // anything else but a move number is a move
+                    let san = symbol.trim_end_matches(['!', '?']);
+                    tokens.push(Token::Move(san.to_string(), suffix_nag(&symbol[san.len()..])));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '.' | '{' | '}' | '(' | ')' | ';' | '[' | ']')
+}
+
+// [Name "value"], with \" and \\ escapes in the value
+fn tag_pair(text: &str) -> Result<Token, PgnErrorKind> {
+    let invalid = || PgnErrorKind::InvalidTag(text.to_string());
+
+    let inner =
+        text.strip_prefix('[').and_then(|text| text.strip_suffix(']')).ok_or_else(invalid)?;
+    let (name, value) = inner.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"'));
+
+    let mut unescaped = String::new();
+    let mut chars = value.ok_or_else(invalid)?.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' { chars.next().ok_or_else(invalid)? } else { c });
+    }
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(invalid());
+    }
+    Ok(Token::Tag(name.to_string(), unescaped))
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    notation: Notation,
+    result: Option<GameResult>,
+}
+
+impl Parser {
+    // read moves until the end of the line: ')' in a variation, the result or the end of the
+    // game in the main line
+    fn line(
+        &mut self,
+        position: &mut Position,
+        depth: usize,
+    ) -> Result<Vec<PgnNode>, PgnErrorKind> {
+        let mut line: Vec<PgnNode> = Vec::new();
+        let mut starting_comment: Option<String> = None;
+
+        while let Some(token) = self.tokens.next() {
+            match token {
+                Token::Move(san, nag) => {
+                    let mv =
+                        san::parse_san_with(position, &san, self.notation).map_err(|error| {
+                            PgnErrorKind::InvalidMove { number: move_number(position), error }
+                        })?;
+
+                    let mut node = PgnNode::new(mv);
+                    node.starting_comment = starting_comment.take();
+                    node.nags.extend(nag);
+                    position.make_move(mv);
+                    line.push(node);
+                }
+                Token::Nag(nag) => match line.last_mut() {
+                    Some(node) => node.nags.push(nag),
+                    None => return Err(PgnErrorKind::UnexpectedToken(format!("${}", nag))),
+                },
+                Token::Comment(text) => match line.last_mut() {
+                    Some(node) => node.add_comment(&text),
+                    None => {
+                        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                        starting_comment = match starting_comment.take() {
+                            Some(comment) => Some(format!("{} {}", comment, text)),
+                            None => Some(text),
+                        };
+                    }
+                },
+                Token::Open => {
+                    let last = line.last_mut().ok_or(PgnErrorKind::UnexpectedToken("(".into()))?;
+
+                    let mut before = position.clone();
+                    before.unmake_move(last.mv);
+                    let variation = self.line(&mut before, depth + 1)?;
+                    if !variation.is_empty() {
+                        last.variations.push(variation);
+                    }
+                }
+                Token::Close if depth > 0 => return Ok(line),
+                Token::Result(result) if depth == 0 => {
+                    self.result = Some(result);
+                    return Ok(line);
+                }
+                Token::Close => return Err(PgnErrorKind::UnexpectedToken(")".into())),
+                // the game ended inside a variation
+                Token::Result(_) => return Err(PgnErrorKind::UnterminatedVariation),
+                Token::Tag(..) => return Err(PgnErrorKind::UnexpectedToken("[".into())),
+            }
+        }
+
+        match depth {
+            0 => Ok(line),
+            _ => Err(PgnErrorKind::UnterminatedVariation),
+        }
+    }
+}
+
+// eg. 12. for white and 12... for black
+fn move_number(position: &Position) -> String {
+    match position.side_to_move {
+        Colour::White => format!("{}.", position.full_move_count),
+        _ => format!("{}...", position.full_move_count),
+    }
+}
+
+// parse the text of a single game and replay its moves
+pub fn parse_game(text: &str, notation: Notation) -> Result<PgnGame, PgnErrorKind> {
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+
+    let mut tags = Vec::new();
+    while let Some(Token::Tag(name, value)) =
+        tokens.next_if(|token| matches!(token, Token::Tag(..)))
+    {
+        tags.push((name, value));
+    }
+    let tag =
+        |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+
+    let mut start = match tag("FEN") {
+        Some(fen) => Position::from_fen(fen).map_err(PgnErrorKind::InvalidFen)?,
+        None => Position::from_fen(crate::fen::START_POS).expect("start position is valid"),
+    };
+    let problems = start.validate();
+    if !problems.is_empty() {
+        return Err(PgnErrorKind::IllegalPosition(problems));
+    }
+    if tag("Variant").is_some_and(|variant| {
+        let variant = variant.to_lowercase();
+        variant.contains("960") || variant.contains("fischer")
+    }) {
+        start.chess960 = true;
+    }
+
+    let tag_result = tag("Result").and_then(GameResult::from_token);
+    let mut parser =
+        Parser { tokens: tokens.collect::<Vec<_>>().into_iter(), notation, result: None };
+
+    let mut position = start.clone();
+    let moves = parser.line(&mut position, 0)?;
+    let result = parser.result.or(tag_result).unwrap_or(GameResult::Ongoing);
+
+    Ok(PgnGame { tags, start, moves, result })
+}
+
+// streaming reader yielding one game at a time, a malformed game is returned as an error and
+// reading continues with the next one
+pub struct PgnReader<R> {
+    lines: io::Lines<R>,
+    notation: Notation,
+    // line read ahead that starts the next game, with its number
+    pending: Option<(usize, String)>,
+    line: usize,
+    games: usize,
+    failed: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader::with_notation(reader, Notation::English)
+    }
+
+    // read games with localized or figurine SAN
+    pub fn with_notation(reader: R, notation: Notation) -> PgnReader<R> {
+        PgnReader {
+            lines: reader.lines(),
+            notation,
+            pending: None,
+            line: 0,
+            games: 0,
+            failed: false,
+        }
+    }
+
+    fn next_line(&mut self) -> Option<io::Result<(usize, String)>> {
+        if let Some(pending) = self.pending.take() {
+            return Some(Ok(pending));
+        }
+        let line = self.lines.next()?;
+        self.line += 1;
+        Some(line.map(|line| (self.line, line)))
+    }
+
+    // collect the lines of the next game: a game ends before the next tag section or after a
+    // line ending in a result outside of comments and variations
+    fn read_game(&mut self) -> io::Result<Option<(usize, String)>> {
+        let mut text = String::new();
+        let mut first = 0;
+        let mut in_comment = false;
+        let mut depth = 0i32;
+        let mut movetext = false;
+
+        while let Some(line) = self.next_line() {
+            let (number, line) = line?;
+            let trimmed = line.trim();
+
+            if !in_comment {
+                // escaped lines are ignored
+                if trimmed.starts_with('%') || (trimmed.is_empty() && text.is_empty()) {
+                    continue;
+                }
+                if trimmed.starts_with('[') && movetext {
+                    self.pending = Some((number, line));
+                    break;
+                }
+            }
+
+            if text.is_empty() {
+                first = number;
+            }
+            text.push_str(&line);
+            text.push('\n');
+
+            let starts_in_comment = in_comment;
+            let mut last_token = String::new();
+            for c in line.chars() {
+                match (in_comment, c) {
+                    (true, '}') => in_comment = false,
+                    (true, _) => {}
+                    (false, '{') => in_comment = true,
+                    (false, ';') => break,
+                    (false, '(') => depth += 1,
+                    (false, ')') => depth -= 1,
+                    _ => {}
+                }
+                if !in_comment && !c.is_whitespace() && c != '}' {
+                    last_token.push(c);
+                } else if c.is_whitespace() {
+                    last_token.clear();
+                }
+            }
+
+            if starts_in_comment || !trimmed.starts_with('[') {
+                movetext = true;
+            }
+            if !in_comment && depth <= 0 && GameResult::from_token(&last_token).is_some() {
+                break;
+            }
+        }
+
+        Ok((!text.trim().is_empty()).then_some((first, text)))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let (line, text) = match self.read_game() {
+            Ok(Some(game)) => game,
+            Ok(None) => return None,
+            Err(err) => {
+                self.failed = true;
+                let kind = PgnErrorKind::Io(err.to_string());
+                return Some(Err(PgnError { game: self.games + 1, line: self.line, kind }));
+            }
+        };
+
+        self.games += 1;
+        let game = self.games;
+        Some(parse_game(&text, self.notation).map_err(|kind| PgnError { game, line, kind }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movegen::generate_legal_moves;
+    use crate::pgn::*;
+
+    const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+[Annotator "a \"quoted\" name"]
+
+{Morphy plays the Opera game.} 1. e4 {[%clk 0:10:00]} e5 {[%clk 0:09:58.5]
+[%eval 0.25]} 2. Nf3 d6 3. d4 Bg4 $6 4. dxe5 Bxf3 (4... dxe5 5. Qxd8+ Kxd8 6. Nxe5 (6.
+Bc4?! {another} Ke8) 6... Be6) 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5
+10. Nxb5! cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7
+16. Qb8+!! Nxb8 17. Rd8# {[%eval #0] [%csl Gd8]} 1-0
+"#;
+
+    fn games(text: &str) -> Vec<Result<PgnGame, PgnError>> {
+        PgnReader::new(text.as_bytes()).collect()
+    }
+
+    #[test]
+    fn check_pgn_game() {
+        let game = games(OPERA_GAME).remove(0).unwrap();
+
+        assert_eq!(game.tags.len(), 8);
+        assert_eq!(game.tag("White"), Some("Paul Morphy"));
+        assert_eq!(game.tag("Annotator"), Some("a \"quoted\" name"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.moves.len(), 33);
+
+        let end = game.end_position();
+        assert!(end.in_check() && generate_legal_moves(&end).is_empty());
+
+        let first = &game.moves[0];
+        assert_eq!(first.starting_comment.as_deref(), Some("Morphy plays the Opera game."));
+        assert_eq!(first.clock, Some(Duration::from_secs(600)));
+        assert_eq!(game.moves[1].clock, Some(Duration::from_millis(598_500)));
+        assert_eq!(game.moves[1].eval, Some(Eval::Centipawns(25)));
+        assert_eq!(game.moves[1].comment, None);
+        assert_eq!(game.moves[5].nags, [6]);
+        assert_eq!(game.moves[18].nags, [1]);
+        assert_eq!(game.moves[30].nags, [3]);
+
+        let last = &game.moves[32];
+        assert_eq!(last.eval, Some(Eval::Mate(0)));
+        assert_eq!(last.comment.as_deref(), Some("[%csl Gd8]"));
+
+        // 4... dxe5 instead of Bxf3, with 6. Bc4 nested inside it
+        let variation = &game.moves[7].variations[0];
+        assert_eq!(variation.len(), 5);
+        assert_eq!(variation[0].mv.to_string(), "d6e5");
+        assert_eq!(variation[3].variations[0].len(), 2);
+        assert_eq!(variation[3].variations[0][0].nags, [6]);
+        assert_eq!(variation[3].variations[0][0].comment.as_deref(), Some("another"));
+    }
+
+    #[test]
+    fn check_pgn_stream() {
+        let text = format!(
+            "{}\n{}\n{}\n{}\n{}",
+            "[Event \"illegal\"]\n\n1. e4 e5 2. Ke3 *",
+            "[Event \"from FEN\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]\n\n30... Kd7 31. e4 1/2-1/2",
+            "1. d4 d5 ) 2. c4 0-1",
+            "1. e4 ; a line comment\n1... c5 *\n\n1. c4 *",
+            OPERA_GAME,
+        );
+        let games = games(&text);
+        assert_eq!(games.len(), 6);
+
+        let err = games[0].as_ref().unwrap_err();
+        assert_eq!((err.game, err.line), (1, 1));
+        let error = SanError::IllegalMove("Ke3".to_string());
+        assert_eq!(err.kind, PgnErrorKind::InvalidMove { number: "2.".to_string(), error });
+
+        let game = games[1].as_ref().unwrap();
+        assert_eq!(game.start.side_to_move, Colour::Black);
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(game.end_position().to_fen(), "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 31");
+
+        let err = games[2].as_ref().unwrap_err();
+        assert_eq!(err.kind, PgnErrorKind::UnexpectedToken(")".to_string()));
+
+        // games without tags are split after their result
+        assert_eq!(games[3].as_ref().unwrap().moves.len(), 2);
+        assert_eq!(games[4].as_ref().unwrap().moves.len(), 1);
+        assert_eq!(games[5].as_ref().unwrap().moves.len(), 33);
+    }
+
+    #[test]
+    fn check_malformed_games() {
+        let kind = |text: &str| parse_game(text, Notation::English).unwrap_err();
+
+        assert_eq!(kind("1. e4 (1. d4 d5 *"), PgnErrorKind::UnterminatedVariation);
+        assert_eq!(kind("1. e4 {open *"), PgnErrorKind::UnterminatedComment);
+        assert_eq!(kind("( 1. e4 ) *"), PgnErrorKind::UnexpectedToken("(".to_string()));
+        assert_eq!(
+            kind("[Event \"x] 1. e4 *"),
+            PgnErrorKind::InvalidTag("[Event \"x]".to_string())
+        );
+        assert!(matches!(kind("[FEN \"8/8 w - -\"] *"), PgnErrorKind::InvalidFen(_)));
+
+        // a game set up without a white king is reported and the next game is still read
+        let text =
+            "[FEN \"4k3/8/8/8/8/8/8/8 w - - 0 1\"]\n\n1. Kb8 *\n\n[Event \"next\"]\n\n1. e4 *";
+        let games = games(text);
+        assert_eq!(games.len(), 2);
+        let problem = Problem::KingCount { colour: Colour::White, count: 0 };
+        assert_eq!(
+            games[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::IllegalPosition(vec![problem])
+        );
+        assert_eq!(games[1].as_ref().unwrap().moves.len(), 1);
+    }
+
+    #[test]
+    fn check_pgn_export() {
+        let game = games(OPERA_GAME).remove(0).unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+        let words = pgn.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(words.contains(
+            "4. dxe5 Bxf3 (4... dxe5 5. Qxd8+ Kxd8 6. Nxe5 (6. Bc4 $6 {another} 6... Ke8)"
+        ));
+        assert_eq!(games(&pgn).remove(0).unwrap(), game);
+
+        let text = "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"] [White \"A \\\"B\\\"\"]
+            30... Kd7 (30... Kf7 31. e4) 31. e4! {[%clk 0:01:00.25] good} Ke6 {[%eval -0.5]} *";
+        let game = parse_game(text, Notation::English).unwrap();
+        assert_eq!(
+            game.to_pgn(),
+            r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "A \"B\""]
+[Black "?"]
+[Result "*"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 (30... Kf7 31. e4) 31. e4 $1 {[%clk 0:01:00.25] good} 31... Ke6
+{[%eval -0.50]} *
+"#
+        );
+
+        let mut game = PgnGame::new(Position::from_fen(crate::fen::START_POS).unwrap());
+        game.set_tag("White", "kage");
+        game.moves.push(PgnNode::new(game.start.parse_san("e4").unwrap()));
+        game.result = GameResult::Draw;
+        assert!(game
+            .to_pgn()
+            .ends_with("[White \"kage\"]\n[Black \"?\"]\n[Result \"1/2-1/2\"]\n\n1. e4 1/2-1/2\n"));
+    }
+
+    #[test]
+    fn check_localized_pgn() {
+        let text = "1. e4 e5 2. Sf3 Sc6 3. Lb5 a6 4. O-O *";
+        let game = PgnReader::with_notation(text.as_bytes(), Notation::German).next().unwrap();
+        assert_eq!(game.unwrap().moves.len(), 7);
+    }
+}
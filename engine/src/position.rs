@@ -0,0 +1,716 @@
+use crate::bitboard::*;
+use crate::fen::{self, FenError};
+use crate::movegen::legal::en_passant_victim;
+use crate::movegen::*;
+use crate::san::{self, SanError};
+use crate::see;
+use crate::validate::{self, Problem};
+use crate::zobrist::{self, KEYS};
+use crate::{Colour, GameStatus, Kind, Piece, Square};
+
+// state that cannot be recovered from a move when taking it back
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Undo {
+    castling_id: [Option<usize>; 4],
+    en_passant: Option<Square>,
+    half_move_clock: u16,
+    hash: u64,
+}
+
+// board state used by move generation and search, keeps the mailbox and bitboards in sync
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub pieces: [Option<Piece>; 64],
+    // indexed by [colour][kind]
+    pub bitboards: [[BitBoard; 6]; 2],
+    // indexed by colour, [Colour::Undefined] holds all pieces
+    pub occupancy: [BitBoard; 3],
+    pub side_to_move: Colour,
+    // file of the castling rook per right, see GameStatus
+    pub castling_id: [Option<usize>; 4],
+    pub en_passant: Option<Square>,
+    pub half_move_clock: u16,
+    pub full_move_count: u16,
+    // castling follows Chess960 rules and castling moves are written king-takes-rook
+    pub chess960: bool,
+    // zobrist keys of the whole position, of the pawns only and of the material signature
+    pub hash: u64,
+    pub pawn_hash: u64,
+    pub material_key: u64,
+    history: Vec<Undo>,
+}
+
+impl Position {
+    pub fn empty() -> Position {
+        Position {
+            pieces: [None; 64],
+            bitboards: [[BitBoard::empty(); 6]; 2],
+            occupancy: [BitBoard::empty(); 3],
+            side_to_move: Colour::White,
+            castling_id: [None; 4],
+            en_passant: None,
+            half_move_clock: 0,
+            full_move_count: 1,
+            chess960: false,
+            hash: 0,
+            pawn_hash: 0,
+            material_key: 0,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        Ok(Position::from(&fen::return_state(fen)?))
+    }
+
+    // convert back into the plain FEN record
+    pub fn to_status(&self) -> GameStatus {
+        GameStatus {
+            pieces: self.pieces,
+            side_to_move: self.side_to_move,
+            castling_id: self.castling_id,
+            en_passant: self.en_passant.map(|sq| vec![sq]),
+            half_move_clock: self.half_move_clock,
+            full_move_count: self.full_move_count,
+            chess960: self.chess960,
+        }
+    }
+
+    pub fn to_fen(&self) -> String {
+        fen::to_fen(&self.to_status())
+    }
+
+    pub fn to_shredder_fen(&self) -> String {
+        fen::to_shredder_fen(&self.to_status())
+    }
+
+    // find the legal move written in UCI notation, castling as set by `chess960`
+    pub fn parse_uci(&self, uci: &str) -> Option<Move> {
+        generate_legal_moves(self).iter().find(|mv| mv.to_uci(self.chess960) == uci).copied()
+    }
+
+    // FEN with en passant squares and castling rights that can never be used removed, so that
+    // the same position always gives the same string
+    pub fn to_normalised_fen(&self) -> String {
+        let mut position = self.clone();
+        position.normalise();
+        position.to_fen()
+    }
+
+    // drop the en passant square if no pawn can legally capture on it, and castling rights
+    // whose king or rook is not on its home square
+    pub fn normalise(&mut self) {
+        if self.en_passant.is_some() {
+            let moves = generate_legal_moves(self);
+            if !moves.iter().any(|mv| mv.flag == MoveFlag::EnPassant) {
+                self.en_passant = None;
+            }
+        }
+
+        for right in 0..4 {
+            if !self.castling_intact(right) {
+                self.castling_id[right] = None;
+            }
+        }
+
+        self.hash = self.compute_hash();
+    }
+
+    // standard algebraic notation of a legal move, eg. Nbd7 or e8=Q+
+    pub fn to_san(&self, mv: Move) -> String {
+        san::to_san(self, mv)
+    }
+
+    pub fn parse_san(&self, text: &str) -> Result<Move, SanError> {
+        san::parse_san(self, text)
+    }
+
+    // home square of the rook of a castling right (indexed like castling_id)
+    pub fn castling_rook_square(&self, right: usize) -> Option<Square> {
+        let rank = if right < 2 { 7 } else { 0 };
+        self.castling_id[right].map(|file| Square::ALL[rank * 8 + file])
+    }
+
+    // check that the king and rook of a castling right are where castling needs them: both on
+    // the back rank with the rook on the right side of the king, and outside of Chess960 the
+    // king on the e file
+    pub fn castling_intact(&self, right: usize) -> bool {
+        let colour = if right < 2 { Colour::White } else { Colour::Black };
+        let kings = self.pieces_of(colour, Kind::King);
+
+        let rook = match self.castling_rook_square(right) {
+            Some(rook) if self.piece_at(rook) == Some(Piece::new(Kind::Rook, colour)) => rook,
+            _ => return false,
+        };
+        if kings.count_bits() != 1 {
+            return false;
+        }
+
+        let king = kings.lsb().expect("one king") as usize;
+        let rook = rook as usize;
+        king / 8 == rook / 8
+            && (king < rook) == right.is_multiple_of(2)
+            && (self.chess960 || king % 8 == 4)
+    }
+
+    // number of earlier occurrences of this position, only looking back to the last capture or
+    // pawn move (and not past the position the game was set up from)
+    pub fn repetitions(&self) -> usize {
+        let plies = self.history.len();
+        let reversible = (self.half_move_clock as usize).min(plies);
+
+        (4..=reversible)
+            .step_by(2)
+            .filter(|back| self.history[plies - back].hash == self.hash)
+            .count()
+    }
+
+    // neither side can checkmate with any series of legal moves: bare kings, a single minor
+    // piece, or only bishops that all stand on squares of one colour
+    pub fn insufficient_material(&self) -> bool {
+        let both = |kind| self.pieces_of(Colour::White, kind) | self.pieces_of(Colour::Black, kind);
+        let (knights, bishops) = (both(Kind::Knight), both(Kind::Bishop));
+
+        if !(both(Kind::Pawn) | both(Kind::Rook) | both(Kind::Queen)).is_empty() {
+            return false;
+        }
+        (knights | bishops).count_bits() <= 1
+            || (knights.is_empty()
+                && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty()))
+    }
+
+    // the side cannot checkmate with any series of legal moves, either with nothing but the king
+    // or with material that is insufficient for both sides (eg. a lone minor piece against a
+    // bare king), anything else can mate with the help of the opponent's pieces
+    pub fn insufficient_material_for(&self, colour: Colour) -> bool {
+        self.occupancy[colour as usize] == self.pieces_of(colour, Kind::King)
+            || self.insufficient_material()
+    }
+
+    // static exchange evaluation of a move, see see::see
+    pub fn see(&self, mv: Move) -> i32 {
+        see::see(self, mv)
+    }
+
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        see::see_ge(self, mv, threshold)
+    }
+
+    // the piece on the square can be won by the opponent
+    pub fn is_hanging(&self, sq: Square) -> bool {
+        see::is_hanging(self, sq)
+    }
+
+    // everything that makes the position unreachable in a legal game
+    pub fn validate(&self) -> Vec<Problem> {
+        validate::validate(self)
+    }
+
+    // zobrist hash computed from scratch, should always equal the incremental one
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if let Some(piece) = piece {
+                hash ^= zobrist::piece(piece.colour, piece.kind, Square::ALL[i]);
+            }
+        }
+        if self.side_to_move == Colour::Black {
+            hash ^= KEYS.side;
+        }
+        hash ^ zobrist::castling(self.castling_id) ^ self.en_passant_key()
+    }
+
+    pub fn compute_pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if let Some(piece) = piece.filter(|piece| piece.kind == Kind::Pawn) {
+                hash ^= zobrist::piece(piece.colour, piece.kind, Square::ALL[i]);
+            }
+        }
+        hash
+    }
+
+    pub fn compute_material_key(&self) -> u64 {
+        let mut key = 0;
+
+        for colour in [Colour::Black, Colour::White] {
+            for kind in
+                [Kind::Pawn, Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen, Kind::King]
+            {
+                for n in 0..self.pieces_of(colour, kind).count_bits() {
+                    key ^= zobrist::material(colour, kind, n);
+                }
+            }
+        }
+        key
+    }
+
+    fn en_passant_key(&self) -> u64 {
+        let side = self.side_to_move;
+        zobrist::en_passant(self.en_passant, side, self.pieces_of(side, Kind::Pawn))
+    }
+
+    fn debug_check_keys(&self) {
+        debug_assert_eq!(self.hash, self.compute_hash(), "incremental hash");
+        debug_assert_eq!(self.pawn_hash, self.compute_pawn_hash(), "incremental pawn hash");
+        debug_assert_eq!(
+            self.material_key,
+            self.compute_material_key(),
+            "incremental material key"
+        );
+    }
+
+    pub fn piece_at(&self, sq: Square) -> Option<Piece> {
+        self.pieces[sq as usize]
+    }
+
+    // bitboard of one piece type of one colour
+    pub fn pieces_of(&self, colour: Colour, kind: Kind) -> BitBoard {
+        self.bitboards[colour as usize][kind as usize]
+    }
+
+    // None if there is no king, positions are not required to be valid
+    pub fn king_square(&self, colour: Colour) -> Option<Square> {
+        self.pieces_of(colour, Kind::King).lsb()
+    }
+
+    // pieces of both colours attacking a square, sliders see through the given occupancy
+    pub fn attackers_to(&self, sq: Square, occ: BitBoard) -> BitBoard {
+        attackers_to(sq, &self.bitboards, occ)
+    }
+
+    // enemy pieces giving check to the king of the side to move, none without a king
+    pub fn checkers(&self) -> BitBoard {
+        let side = self.side_to_move;
+        match self.king_square(side) {
+            Some(king) => {
+                self.attackers_to(king, self.occupancy[2])
+                    & self.occupancy[side.opposite() as usize]
+            }
+            None => BitBoard::empty(),
+        }
+    }
+
+    // check if the side to move is in check
+    pub fn in_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+
+    // pieces of the given colour that are absolutely pinned to their own king
+    pub fn pinned(&self, colour: Colour) -> BitBoard {
+        let king = match self.king_square(colour) {
+            Some(king) => king,
+            None => return BitBoard::empty(),
+        };
+        let enemy = colour.opposite();
+        let mut pinned = BitBoard::empty();
+
+        // enemy sliders that would attack the king on an empty board
+        let straight = self.pieces_of(enemy, Kind::Rook) | self.pieces_of(enemy, Kind::Queen);
+        let diagonal = self.pieces_of(enemy, Kind::Bishop) | self.pieces_of(enemy, Kind::Queen);
+        let snipers = (rook(king, BitBoard::empty()) & straight)
+            | (bishop(king, BitBoard::empty()) & diagonal);
+
+        for sniper in snipers {
+            let blockers = rays::between(king, sniper) & self.occupancy[2];
+            if blockers.count_bits() == 1
+                && !(blockers & self.occupancy[colour as usize]).is_empty()
+            {
+                pinned |= blockers;
+            }
+        }
+        pinned
+    }
+
+    // squares a piece may move to without exposing its own king, the line through the king
+    // for pinned pieces and the whole board otherwise
+    pub fn pin_ray(&self, sq: Square) -> BitBoard {
+        let piece = match self.piece_at(sq) {
+            Some(piece) => piece,
+            None => return UNIVERSAL,
+        };
+
+        match self.king_square(piece.colour) {
+            Some(king) if self.pinned(piece.colour).get_bit(sq) => rays::line(king, sq),
+            _ => UNIVERSAL,
+        }
+    }
+
+    fn put_piece(&mut self, sq: Square, piece: Piece) {
+        let key = zobrist::piece(piece.colour, piece.kind, sq);
+        let count = self.pieces_of(piece.colour, piece.kind).count_bits();
+
+        self.hash ^= key;
+        if piece.kind == Kind::Pawn {
+            self.pawn_hash ^= key;
+        }
+        self.material_key ^= zobrist::material(piece.colour, piece.kind, count);
+
+        self.pieces[sq as usize] = Some(piece);
+        self.bitboards[piece.colour as usize][piece.kind as usize].set_bit(sq);
+        self.occupancy[piece.colour as usize].set_bit(sq);
+        self.occupancy[2].set_bit(sq);
+    }
+
+    fn remove_piece(&mut self, sq: Square) -> Piece {
+        let piece = self.pieces[sq as usize].take().expect("no piece to remove");
+        self.bitboards[piece.colour as usize][piece.kind as usize].toggle_bit(sq);
+        self.occupancy[piece.colour as usize].toggle_bit(sq);
+        self.occupancy[2].toggle_bit(sq);
+
+        let key = zobrist::piece(piece.colour, piece.kind, sq);
+        let count = self.pieces_of(piece.colour, piece.kind).count_bits();
+
+        self.hash ^= key;
+        if piece.kind == Kind::Pawn {
+            self.pawn_hash ^= key;
+        }
+        self.material_key ^= zobrist::material(piece.colour, piece.kind, count);
+        piece
+    }
+
+    fn move_piece(&mut self, from: Square, to: Square) {
+        let piece = self.remove_piece(from);
+        self.put_piece(to, piece);
+    }
+
+    // castling rights are lost once the king moves or a rook leaves (or is captured on) its home
+    // square
+    fn update_castling(&mut self, mv: &Move, side: Colour) {
+        for right in 0..4 {
+            let own = (right < 2) == (side == Colour::White);
+            let rook = self.castling_rook_square(right);
+
+            if (own && mv.piece == Kind::King) || rook == Some(mv.from) || rook == Some(mv.to) {
+                self.castling_id[right] = None;
+            }
+        }
+    }
+
+    pub fn make_move(&mut self, mv: Move) {
+        let side = self.side_to_move;
+
+        self.history.push(Undo {
+            castling_id: self.castling_id,
+            en_passant: self.en_passant,
+            half_move_clock: self.half_move_clock,
+            hash: self.hash,
+        });
+
+        self.hash ^= self.en_passant_key() ^ zobrist::castling(self.castling_id);
+
+        if mv.piece == Kind::Pawn || mv.is_capture() {
+            self.half_move_clock = 0;
+        } else {
+            self.half_move_clock += 1;
+        }
+
+        match mv.flag {
+            MoveFlag::Castle => {
+                // in Chess960 the king and rook may land on each other's squares, lift both first
+                let (king_to, rook_to) = castling_squares(mv.from, mv.to);
+                let king = self.remove_piece(mv.from);
+                let rook = self.remove_piece(mv.to);
+                self.put_piece(king_to, king);
+                self.put_piece(rook_to, rook);
+            }
+            MoveFlag::EnPassant => {
+                self.remove_piece(en_passant_victim(&mv));
+                self.move_piece(mv.from, mv.to);
+            }
+            _ => {
+                if mv.is_capture() {
+                    self.remove_piece(mv.to);
+                }
+                self.move_piece(mv.from, mv.to);
+            }
+        }
+
+        if let Some(kind) = mv.promotion {
+            self.remove_piece(mv.to);
+            self.put_piece(mv.to, Piece::new(kind, side));
+        }
+
+        self.en_passant = match mv.flag {
+            MoveFlag::DoublePush => Some(Square::ALL[(mv.from as usize + mv.to as usize) / 2]),
+            _ => None,
+        };
+
+        self.update_castling(&mv, side);
+
+        if side == Colour::Black {
+            self.full_move_count += 1;
+        }
+        self.side_to_move = side.opposite();
+
+        self.hash ^= KEYS.side ^ zobrist::castling(self.castling_id) ^ self.en_passant_key();
+        self.debug_check_keys();
+    }
+
+    pub fn unmake_move(&mut self, mv: Move) {
+        let undo = self.history.pop().expect("no move to take back");
+        let side = self.side_to_move.opposite();
+
+        self.side_to_move = side;
+        if side == Colour::Black {
+            self.full_move_count -= 1;
+        }
+
+        if mv.flag == MoveFlag::Castle {
+            let (king_to, rook_to) = castling_squares(mv.from, mv.to);
+            let king = self.remove_piece(king_to);
+            let rook = self.remove_piece(rook_to);
+            self.put_piece(mv.from, king);
+            self.put_piece(mv.to, rook);
+        } else {
+            if mv.promotion.is_some() {
+                self.remove_piece(mv.to);
+                self.put_piece(mv.to, Piece::new(Kind::Pawn, side));
+            }
+
+            self.move_piece(mv.to, mv.from);
+
+            if let Some(kind) = mv.capture {
+                let victim = match mv.flag {
+                    MoveFlag::EnPassant => en_passant_victim(&mv),
+                    _ => mv.to,
+                };
+                self.put_piece(victim, Piece::new(kind, side.opposite()));
+            }
+        }
+
+        self.castling_id = undo.castling_id;
+        self.en_passant = undo.en_passant;
+        self.half_move_clock = undo.half_move_clock;
+        self.hash = undo.hash;
+        self.debug_check_keys();
+    }
+
+    // pass the turn without moving (used for null move pruning)
+    pub fn make_null_move(&mut self) {
+        self.history.push(Undo {
+            castling_id: self.castling_id,
+            en_passant: self.en_passant,
+            half_move_clock: self.half_move_clock,
+            hash: self.hash,
+        });
+
+        self.hash ^= self.en_passant_key() ^ KEYS.side;
+        self.en_passant = None;
+        self.half_move_clock += 1;
+        self.side_to_move = self.side_to_move.opposite();
+        self.debug_check_keys();
+    }
+
+    pub fn unmake_null_move(&mut self) {
+        let undo = self.history.pop().expect("no move to take back");
+
+        self.en_passant = undo.en_passant;
+        self.half_move_clock = undo.half_move_clock;
+        self.hash = undo.hash;
+        self.side_to_move = self.side_to_move.opposite();
+    }
+}
+
+impl From<&GameStatus> for Position {
+    fn from(state: &GameStatus) -> Position {
+        let mut position = Position::empty();
+
+        for (i, piece) in state.pieces.iter().enumerate() {
+            if let Some(piece) = piece {
+                position.put_piece(Square::ALL[i], *piece);
+            }
+        }
+
+        position.side_to_move = state.side_to_move;
+        position.castling_id = state.castling_id;
+        // an en passant square without a pawn that just passed over it cannot be played, drop it
+        // instead of generating a capture of a missing pawn
+        position.en_passant = state
+            .en_passant
+            .as_ref()
+            .and_then(|squares| squares.first().copied())
+            .filter(|ep| validate::en_passant_possible(&position, *ep));
+        position.half_move_clock = state.half_move_clock;
+        position.full_move_count = state.full_move_count;
+        position.chess960 = state.chess960;
+        position.hash = position.compute_hash();
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen;
+    use crate::movegen::generate_legal_moves;
+    use crate::position::*;
+
+    // play every move two plies deep and check that taking it back restores the position
+    fn check_restore(position: &mut Position, depth: u32) {
+        if depth == 0 {
+            return;
+        }
+
+        for mv in &generate_legal_moves(position) {
+            let before = position.clone();
+            position.make_move(*mv);
+
+            let mut rebuilt = position.clone();
+            rebuilt.history.clear();
+            assert_eq!(Position::from(&position.to_status()), rebuilt, "after {}", mv);
+
+            check_restore(position, depth - 1);
+            position.unmake_move(*mv);
+            assert_eq!(*position, before, "after taking back {}", mv);
+        }
+    }
+
+    #[test]
+    fn check_make_unmake() {
+        for fen in [fen::START_POS, fen::TRICKY_POS, fen::KILLER_POS, fen::CMK_POS] {
+            check_restore(&mut Position::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn check_transposition_hash() {
+        let mut a = Position::from_fen(fen::START_POS).unwrap();
+        let mut b = Position::from_fen(fen::START_POS).unwrap();
+
+        // 1. Nf3 Nf6 2. Nc3 and 1. Nc3 Nf6 2. Nf3 reach the same position
+        for (position, moves) in
+            [(&mut a, ["g1f3", "g8f6", "b1c3"]), (&mut b, ["b1c3", "g8f6", "g1f3"])]
+        {
+            for name in moves {
+                let mv = *generate_legal_moves(position)
+                    .iter()
+                    .find(|mv| mv.to_string() == name)
+                    .unwrap();
+                position.make_move(mv);
+            }
+        }
+
+        assert_eq!(a.hash, b.hash);
+        assert_eq!(a.material_key, Position::from_fen(fen::START_POS).unwrap().material_key);
+        assert_ne!(a.hash, Position::from_fen(fen::START_POS).unwrap().hash);
+    }
+
+    #[test]
+    fn check_pins_and_checkers() {
+        // the bishop on e2 is pinned by the rook on e7 and cannot move at all
+        let position = Position::from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(position.pinned(Colour::White), BitBoard::from_sq(Square::E2));
+        assert_eq!(position.pin_ray(Square::E2), FILES[4]);
+        assert!(generate_legal_moves(&position).iter().all(|mv| mv.piece == Kind::King));
+
+        // double check from the rook and the knight, only the king may move
+        let position = Position::from_fen("4k3/8/8/8/8/5n2/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(position.checkers().count_bits(), 2);
+        let moves = generate_legal_moves(&position);
+        let names: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+        assert_eq!(names, ["e1e2", "e1f2"]);
+
+        // single check, the knight can block and the bishop can capture
+        let position = Position::from_fen("4k3/8/8/8/8/2B5/5N2/r3K3 w - - 0 1").unwrap();
+        assert_eq!(position.checkers(), BitBoard::from_sq(Square::A1));
+        let moves = generate_legal_moves(&position);
+        assert!(moves.iter().any(|mv| mv.to_string() == "c3a1"));
+        assert!(moves.iter().any(|mv| mv.to_string() == "f2d1"));
+        assert!(!moves.iter().any(|mv| mv.to_string() == "f2d3"));
+    }
+
+    #[test]
+    fn check_fen_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkb1r/pp1p1pPp/8/2p1pP2/1P1P4/3P3P/P1P1P3/RNBQKBNR w KQkq e6 0 1",
+            "r2q1rk1/ppp2ppp/2n1bn2/2b1p3/3pP3/3P1NPP/PPP1NPB1/R1BQ1RK1 b - - 0 9",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 17 42",
+        ];
+        for fen in fens {
+            assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+        }
+
+        // clocks are filled in when missing
+        let position = Position::from_fen("8/8/8/4k3/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(position.to_fen(), "8/8/8/4k3/8/8/8/4K3 w - - 0 1");
+
+        // en passant squares without the pawn that passed over them are dropped
+        for fen in ["4k3/8/8/3P4/8/8/8/4K3 w - e6 0 1", "4k3/8/8/3Pn3/8/8/8/4K3 w - e6 0 1"] {
+            assert_eq!(Position::from_fen(fen).unwrap().en_passant, None);
+        }
+    }
+
+    #[test]
+    fn check_normalised_fen() {
+        // no black pawn next to e4, so the en passant square is dropped
+        let position = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(position.to_normalised_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+
+        // the d4 pawn can capture
+        let fen = "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1";
+        assert_eq!(Position::from_fen(fen).unwrap().to_normalised_fen(), fen);
+
+        // the d4 pawn is pinned against the king and cannot capture
+        let position = Position::from_fen("8/8/8/8/k2pP2R/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(position.to_normalised_fen(), "8/8/8/8/k2pP2R/8/8/4K3 b - - 0 1");
+
+        // white king moved, black queenside rook missing
+        let position = Position::from_fen("1n2k2r/8/8/8/8/8/4K3/R6R w KQkq - 0 1").unwrap();
+        assert_eq!(position.to_normalised_fen(), "1n2k2r/8/8/8/8/8/4K3/R6R w k - 0 1");
+
+        let mut normalised = position.clone();
+        normalised.normalise();
+        assert_eq!(Position::from_fen(&normalised.to_fen()).unwrap().hash, normalised.hash);
+    }
+
+    #[test]
+    fn check_null_move() {
+        let mut position = Position::from_fen(fen::KILLER_POS).unwrap();
+        let before = position.clone();
+
+        position.make_null_move();
+        assert_eq!(position.side_to_move, Colour::Black);
+        assert_eq!(position.en_passant, None);
+
+        position.unmake_null_move();
+        assert_eq!(position, before);
+    }
+
+    #[test]
+    fn check_chess960() {
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let position = Position::from_fen(fen).unwrap();
+        assert!(position.chess960);
+        assert_eq!(position.castling_id, [Some(7), Some(5), Some(7), Some(5)]);
+        assert_eq!(position.to_shredder_fen(), fen);
+        assert_eq!(Position::from_fen(&position.to_fen()).unwrap(), position);
+
+        // X-FEN names the rook by file when it is not the outermost one
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/RR3K2 w B - 0 1").unwrap();
+        assert_eq!(position.castling_id, [None, Some(1), None, None]);
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/RR3K2 w B - 0 1");
+
+        // standard positions are unchanged, castling is the king taking the rook in Chess960 UCI
+        let position = Position::from_fen(fen::TRICKY_POS).unwrap();
+        assert!(!position.chess960);
+        assert_eq!(position.to_shredder_fen(), fen::TRICKY_POS.replace("KQkq", "HAha").trim());
+        let castle = position.parse_uci("e1g1").unwrap();
+        assert_eq!(castle.flag, MoveFlag::Castle);
+        assert_eq!(castle.to_uci(true), "e1h1");
+
+        // king and rook swap squares
+        let mut position =
+            Position::from_fen("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9")
+                .unwrap();
+        let castle = position.parse_uci("f1g1").unwrap();
+        assert_eq!(castle.flag, MoveFlag::Castle);
+        check_restore(&mut position, 2);
+
+        position.make_move(castle);
+        assert_eq!(position.to_fen(), "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRRKB b - - 2 9");
+    }
+}
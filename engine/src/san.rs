@@ -0,0 +1,416 @@
+use crate::bitboard::UNICODE_PIECES;
+use crate::movegen::*;
+use crate::position::Position;
+use crate::{File, Kind, Rank, Square};
+use std::error::Error;
+use std::fmt;
+
+// why a SAN string could not be turned into a move, holding the input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    Empty,
+    // not shaped like a move at all
+    InvalidSyntax(String),
+    // well formed, but no legal move matches
+    IllegalMove(String),
+    // more than one legal move matches, with the candidates in SAN
+    AmbiguousMove { san: String, candidates: Vec<String> },
+    // a pawn reaching the last rank without saying what it promotes to
+    MissingPromotion(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Empty => write!(f, "empty move"),
+            SanError::InvalidSyntax(san) => write!(f, "'{}' is not a move", san),
+            SanError::IllegalMove(san) => write!(f, "'{}' is not a legal move", san),
+            SanError::AmbiguousMove { san, candidates } => {
+                write!(f, "'{}' is ambiguous, it could be {}", san, candidates.join(" or "))
+            }
+            SanError::MissingPromotion(san) => write!(f, "'{}' needs a promotion piece", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+// piece letters used for SAN, English by default
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Notation {
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+    Polish,
+    // Unicode figurines (FAN), eg. ♘f3
+    Figurine,
+}
+
+impl Notation {
+    // symbols of [knight, bishop, rook, queen, king], pawns never get one in SAN
+    fn symbols(&self) -> [&'static str; 5] {
+        match self {
+            Notation::English => ["N", "B", "R", "Q", "K"],
+            Notation::German => ["S", "L", "T", "D", "K"],
+            Notation::French => ["C", "F", "T", "D", "R"],
+            Notation::Spanish | Notation::Italian => ["C", "A", "T", "D", "R"],
+            Notation::Dutch => ["P", "L", "T", "D", "K"],
+            Notation::Polish => ["S", "G", "W", "H", "K"],
+            Notation::Figurine => {
+                let [_, knight, bishop, rook, queen, king, ..] = UNICODE_PIECES;
+                [knight, bishop, rook, queen, king]
+            }
+        }
+    }
+
+    pub fn symbol(&self, kind: Kind) -> &'static str {
+        match kind {
+            Kind::Pawn => "",
+            _ => self.symbols()[kind as usize - 1],
+        }
+    }
+
+    // piece type of a symbol, figurines of either colour are accepted
+    pub fn kind(&self, c: char) -> Option<Kind> {
+        const KINDS: [Kind; 5] = [Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen, Kind::King];
+        let mut buf = [0; 4];
+        let c = &*c.encode_utf8(&mut buf);
+
+        let found = self.symbols().iter().position(|symbol| *symbol == c);
+        let found = match self {
+            Notation::Figurine => {
+                found.or_else(|| UNICODE_PIECES[7..].iter().position(|symbol| *symbol == c))
+            }
+            _ => found,
+        };
+        found.map(|i| KINDS[i])
+    }
+}
+
+// standard algebraic notation of a legal move in the position, eg. Nbd7, exd6, e8=Q+ or O-O-O#
+pub fn to_san(position: &Position, mv: Move) -> String {
+    to_san_with(position, mv, Notation::English)
+}
+
+// SAN with the piece symbols of another language or figurines, eg. Sbd7 in German
+pub fn to_san_with(position: &Position, mv: Move, notation: Notation) -> String {
+    let mut san = match mv.flag {
+        MoveFlag::Castle if (mv.to as u32) > (mv.from as u32) => "O-O".to_string(),
+        MoveFlag::Castle => "O-O-O".to_string(),
+        _ => move_text(position, mv, notation),
+    };
+
+    let mut after = position.clone();
+    after.make_move(mv);
+    if after.in_check() {
+        san.push(if generate_legal_moves(&after).is_empty() { '#' } else { '+' });
+    }
+    san
+}
+
+fn move_text(position: &Position, mv: Move, notation: Notation) -> String {
+    let mut san = String::new();
+    let from = mv.from.to_string();
+
+    if mv.piece == Kind::Pawn {
+        if mv.is_capture() {
+            san.push_str(&from[..1]);
+        }
+    } else {
+        san.push_str(notation.symbol(mv.piece));
+        san.push_str(disambiguation(position, mv, &from));
+    }
+
+    if mv.is_capture() {
+        san.push('x');
+    }
+    san.push_str(&mv.to.to_string());
+
+    if let Some(kind) = mv.promotion {
+        san.push('=');
+        san.push_str(notation.symbol(kind));
+    }
+    san
+}
+
+// the file, the rank or the whole square the piece comes from, when another piece of the same
+// kind could also go to the same square
+fn disambiguation<'a>(position: &Position, mv: Move, from: &'a str) -> &'a str {
+    let moves = generate_legal_moves(position);
+    let others: Vec<&Move> = moves
+        .iter()
+        .filter(|other| other.piece == mv.piece && other.to == mv.to && other.from != mv.from)
+        .filter(|other| other.flag != MoveFlag::Castle)
+        .collect();
+
+    if others.is_empty() {
+        ""
+    } else if others.iter().all(|other| other.from.file() != mv.from.file()) {
+        &from[..1]
+    } else if others.iter().all(|other| other.from.rank() != mv.from.rank()) {
+        &from[1..]
+    } else {
+        from
+    }
+}
+
+// a SAN move split into its parts
+struct Parsed {
+    piece: Kind,
+    file: Option<File>,
+    rank: Option<Rank>,
+    to: Square,
+    promotion: Option<Kind>,
+}
+
+// find the legal move for a SAN string. Accepts common variations: 0-0 for O-O, a missing or
+// extra capture sign, long algebraic (Ng1-f3), promotions without '=' (e8Q, e8q, e8(Q)) and
+// trailing check signs and annotations (+, #, !, ?, e.p.)
+pub fn parse_san(position: &Position, san: &str) -> Result<Move, SanError> {
+    parse_san_with(position, san, Notation::English)
+}
+
+// parse SAN written with the piece symbols of the given notation
+pub fn parse_san_with(
+    position: &Position,
+    san: &str,
+    notation: Notation,
+) -> Result<Move, SanError> {
+    let text = strip_annotations(san.trim());
+    if text.is_empty() {
+        return Err(SanError::Empty);
+    }
+
+    let moves = generate_legal_moves(position);
+
+    let castle = match text {
+        "O-O" | "0-0" | "o-o" => Some(true),
+        "O-O-O" | "0-0-0" | "o-o-o" => Some(false),
+        _ => None,
+    };
+    if let Some(kingside) = castle {
+        return moves
+            .iter()
+            .find(|mv| {
+                mv.flag == MoveFlag::Castle && ((mv.to as u32) > (mv.from as u32)) == kingside
+            })
+            .copied()
+            .ok_or_else(|| SanError::IllegalMove(san.to_string()));
+    }
+
+    let parsed =
+        parse_parts(text, notation).ok_or_else(|| SanError::InvalidSyntax(san.to_string()))?;
+
+    let candidates: Vec<Move> = moves
+        .iter()
+        .filter(|mv| mv.flag != MoveFlag::Castle && mv.piece == parsed.piece && mv.to == parsed.to)
+        .filter(|mv| parsed.file.is_none_or(|file| mv.from.file() == file))
+        .filter(|mv| parsed.rank.is_none_or(|rank| mv.from.rank() == rank))
+        .filter(|mv| parsed.promotion.is_none() || mv.promotion == parsed.promotion)
+        .copied()
+        .collect();
+
+    match candidates.as_slice() {
+        [] => Err(SanError::IllegalMove(san.to_string())),
+        [mv] if mv.promotion.is_some() && parsed.promotion.is_none() => {
+            Err(SanError::MissingPromotion(san.to_string()))
+        }
+        [mv] => Ok(*mv),
+        // all four promotions of one pawn
+        [mv, ..]
+            if candidates
+                .iter()
+                .all(|other| other.from == mv.from && other.promotion.is_some()) =>
+        {
+            Err(SanError::MissingPromotion(san.to_string()))
+        }
+        _ => Err(SanError::AmbiguousMove {
+            san: san.to_string(),
+            candidates: candidates.iter().map(|mv| to_san_with(position, *mv, notation)).collect(),
+        }),
+    }
+}
+
+fn strip_annotations(text: &str) -> &str {
+    let text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+    text.trim_end_matches(['+', '#', '!', '?'])
+}
+
+fn parse_parts(text: &str, notation: Notation) -> Option<Parsed> {
+    let mut chars: Vec<char> =
+        text.chars().filter(|c| !matches!(c, 'x' | 'X' | ':' | '-')).collect();
+
+    // a symbol in any case, unless lowercase would make it a file
+    let any_case = |c: char| notation.kind(c).or_else(|| notation.kind(c.to_ascii_uppercase()));
+
+    // a leading piece symbol
+    let piece = match chars.first() {
+        Some('a'..='h') | None => Kind::Pawn,
+        Some(c) => match any_case(*c) {
+            Some(kind) => {
+                chars.remove(0);
+                kind
+            }
+            None => Kind::Pawn,
+        },
+    };
+
+    // promotion after the destination: =Q, Q, q, (Q) or /Q
+    if chars.last() == Some(&')') {
+        chars.pop();
+    }
+    let mut promotion = None;
+    if let [.., before, last] = chars[..] {
+        let kind = any_case(last).filter(|kind| *kind != Kind::King);
+        if kind.is_some() && matches!(before, '1'..='8' | '=' | '(' | '/') {
+            promotion = kind;
+            chars.pop();
+            if matches!(chars.last(), Some('=' | '(' | '/')) {
+                chars.pop();
+            }
+        }
+    }
+
+    let to = match chars[..] {
+        [.., file @ 'a'..='h', rank @ '1'..='8'] => {
+            chars.truncate(chars.len() - 2);
+            Square::new(File::from_char(file)?, Rank::from_char(rank)?)
+        }
+        _ => return None,
+    };
+
+    // whatever is left can only be the file and/or rank the piece comes from
+    let (file, rank) = match chars[..] {
+        [] => (None, None),
+        [file @ 'a'..='h'] => (File::from_char(file), None),
+        [rank @ '1'..='8'] => (None, Rank::from_char(rank)),
+        [file @ 'a'..='h', rank @ '1'..='8'] => (File::from_char(file), Rank::from_char(rank)),
+        _ => return None,
+    };
+
+    Some(Parsed { piece, file, rank, to, promotion })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen;
+    use crate::san::*;
+
+    fn san(fen: &str, uci: &str) -> String {
+        let position = Position::from_fen(fen).unwrap();
+        position.to_san(position.parse_uci(uci).unwrap())
+    }
+
+    fn uci(fen: &str, san: &str) -> Result<String, SanError> {
+        let position = Position::from_fen(fen).unwrap();
+        position.parse_san(san).map(|mv| mv.to_string())
+    }
+
+    #[test]
+    fn check_san_formatting() {
+        assert_eq!(san(fen::START_POS, "g1f3"), "Nf3");
+        assert_eq!(san(fen::START_POS, "e2e4"), "e4");
+        assert_eq!(san(fen::TRICKY_POS, "e1g1"), "O-O");
+        assert_eq!(san(fen::TRICKY_POS, "e1c1"), "O-O-O");
+        assert_eq!(san(fen::TRICKY_POS, "d5e6"), "dxe6");
+        assert_eq!(san(fen::TRICKY_POS, "e5f7"), "Nxf7");
+
+        // file, rank and full square disambiguation
+        let fen = "4k3/8/8/8/8/1N3N2/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "b3d4"), "Nbd4");
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, "a1a3"), "R1a3");
+        let fen = "4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1";
+        assert_eq!(san(fen, "a3b2"), "Qa3b2");
+
+        // promotions with check and mate
+        assert_eq!(san("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e7e8q"), "e8=Q");
+        assert_eq!(san("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8r"), "e8=R+");
+        assert_eq!(san("k7/8/1K6/8/8/8/8/7R w - - 0 1", "h1h8"), "Rh8#");
+        assert_eq!(san(fen::KILLER_POS, "f5e6"), "fxe6");
+    }
+
+    #[test]
+    fn check_san_parsing() {
+        assert_eq!(uci(fen::START_POS, "Nf3").unwrap(), "g1f3");
+        assert_eq!(uci(fen::START_POS, "e4!?").unwrap(), "e2e4");
+        assert_eq!(uci(fen::START_POS, "Ng1-f3").unwrap(), "g1f3");
+        assert_eq!(uci(fen::TRICKY_POS, "0-0").unwrap(), "e1g1");
+        assert_eq!(uci(fen::TRICKY_POS, "O-O-O").unwrap(), "e1c1");
+        assert_eq!(uci(fen::TRICKY_POS, "de6").unwrap(), "d5e6");
+        assert_eq!(uci(fen::TRICKY_POS, "Nxf7+").unwrap(), "e5f7");
+        assert_eq!(uci(fen::KILLER_POS, "fxe6 e.p.").unwrap(), "f5e6");
+        assert_eq!(uci(fen::KILLER_POS, "gxh8=N").unwrap(), "g7h8n");
+        assert_eq!(uci(fen::KILLER_POS, "gxh8q").unwrap(), "g7h8q");
+        assert_eq!(uci("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8Q").unwrap(), "e7e8q");
+        assert_eq!(uci("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8(R)").unwrap(), "e7e8r");
+
+        assert_eq!(uci(fen::START_POS, ""), Err(SanError::Empty));
+        assert_eq!(uci(fen::START_POS, "Nf9"), Err(SanError::InvalidSyntax("Nf9".to_string())));
+        assert_eq!(uci(fen::START_POS, "e5"), Err(SanError::IllegalMove("e5".to_string())));
+        assert_eq!(uci(fen::START_POS, "O-O"), Err(SanError::IllegalMove("O-O".to_string())));
+        assert_eq!(
+            uci("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8"),
+            Err(SanError::MissingPromotion("e8".to_string()))
+        );
+        assert_eq!(
+            uci("4k3/8/8/8/8/1N3N2/8/4K3 w - - 0 1", "Nd4"),
+            Err(SanError::AmbiguousMove {
+                san: "Nd4".to_string(),
+                candidates: vec!["Nbd4".to_string(), "Nfd4".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn check_san_round_trip() {
+        let notations = [
+            Notation::English,
+            Notation::German,
+            Notation::French,
+            Notation::Spanish,
+            Notation::Italian,
+            Notation::Dutch,
+            Notation::Polish,
+            Notation::Figurine,
+        ];
+
+        for fen in [fen::START_POS, fen::TRICKY_POS, fen::KILLER_POS, fen::CMK_POS] {
+            let position = Position::from_fen(fen).unwrap();
+            for mv in &generate_legal_moves(&position) {
+                for notation in notations {
+                    let san = to_san_with(&position, *mv, notation);
+                    assert_eq!(parse_san_with(&position, &san, notation), Ok(*mv), "{}", san);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_localized_san() {
+        let position = Position::from_fen(fen::KILLER_POS).unwrap();
+        let formatted = |uci: &str, notation| {
+            to_san_with(&position, position.parse_uci(uci).unwrap(), notation)
+        };
+
+        assert_eq!(formatted("g7h8q", Notation::German), "gxh8=D");
+        assert_eq!(formatted("g7h8n", Notation::French), "gxh8=C");
+        assert_eq!(formatted("f1g2", Notation::Spanish), "Ag2");
+        assert_eq!(formatted("f1g2", Notation::Figurine), "♗g2");
+        assert_eq!(formatted("e1d2", Notation::Polish), "Kd2");
+        assert_eq!(formatted("e1f2", Notation::French), "Rf2");
+
+        // symbols are accepted lowercase unless that makes them a file, like the French 'c'
+        let mv = |san, notation| parse_san_with(&position, san, notation).map(|mv| mv.to_string());
+        assert_eq!(mv("Cc3", Notation::French).unwrap(), "b1c3");
+        assert_eq!(mv("sc3", Notation::German).unwrap(), "b1c3");
+        assert_eq!(mv("cc3", Notation::French).unwrap(), "c2c3");
+        assert_eq!(mv("Sc3", Notation::German).unwrap(), "b1c3");
+        assert_eq!(mv("♞c3", Notation::Figurine).unwrap(), "b1c3");
+        assert_eq!(mv("gxh8L", Notation::German).unwrap(), "g7h8b");
+        assert_eq!(mv("Nc3", Notation::German), Err(SanError::InvalidSyntax("Nc3".to_string())));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::eval::{evaluate, PIECE_VALUES};
+use crate::movegen::{generate_legal_moves, Move};
+use crate::position::Position;
+use std::time::{Duration, Instant};
+
+pub const INFINITY: i32 = 32000;
+// score of being mated at the root, mates further away score closer to zero
+pub const MATE: i32 = 31000;
+pub const MAX_PLY: usize = 64;
+
+// when to stop searching, both may be set
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+// result of a finished iteration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    // centipawns from the side to move's point of view
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    // moves until mate, negative when the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        let plies = MATE - self.score.abs();
+        (plies <= MAX_PLY as i32).then(|| (plies + 1) / 2 * self.score.signum())
+    }
+}
+
+struct Searcher {
+    start: Instant,
+    limits: SearchLimits,
+    nodes: u64,
+    stopped: bool,
+    // principal variation of the last finished iteration, tried first
+    previous_pv: Vec<Move>,
+    // best line found from every ply of the current path
+    pv: Vec<Vec<Move>>,
+}
+
+impl Searcher {
+    fn out_of_time(&mut self) -> bool {
+        // checking the clock is slow, only do it every few thousand nodes
+        if self.nodes.is_multiple_of(2048) {
+            if let Some(time) = self.limits.time {
+                self.stopped |= self.start.elapsed() >= time;
+            }
+        }
+        self.stopped
+    }
+
+    // previous principal variation move first, then captures by most valuable victim and
+    // least valuable attacker, then promotions
+    fn order(&self, moves: &mut [Move], ply: usize, on_pv: bool) {
+        let pv_move = self.previous_pv.get(ply).filter(|_| on_pv).copied();
+
+        moves.sort_by_cached_key(|mv| {
+            if Some(*mv) == pv_move {
+                return i32::MIN;
+            }
+            let victim = mv.capture.map_or(0, |kind| PIECE_VALUES[kind as usize] * 10);
+            let attacker = if mv.is_capture() { PIECE_VALUES[mv.piece as usize] / 10 } else { 0 };
+            let promotion = mv.promotion.map_or(0, |kind| PIECE_VALUES[kind as usize]);
+            attacker - victim - promotion
+        });
+    }
+
+    fn negamax(
+        &mut self,
+        position: &mut Position,
+        mut depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        on_pv: bool,
+    ) -> i32 {
+        self.pv[ply].clear();
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let in_check = position.in_check();
+        // look further into checks so that mates are not cut off by the horizon
+        if in_check && ply < MAX_PLY / 2 {
+            depth += 1;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        // a position seen before is treated as a draw right away, if repeating was good it will
+        // be good again
+        if ply > 0
+            && (position.half_move_clock >= 100
+                || position.repetitions() > 0
+                || position.insufficient_material())
+        {
+            return 0;
+        }
+
+        let mut moves = generate_legal_moves(position).as_slice().to_vec();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order(&mut moves, ply, on_pv);
+
+        for (i, mv) in moves.iter().enumerate() {
+            let follows_pv = on_pv && i == 0 && self.previous_pv.get(ply) == Some(mv);
+
+            position.make_move(*mv);
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, follows_pv);
+            position.unmake_move(*mv);
+
+            if self.stopped {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                let (line, rest) = self.pv.split_at_mut(ply + 1);
+                line[ply].clear();
+                line[ply].push(*mv);
+                line[ply].extend_from_slice(&rest[0]);
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+
+    // only captures and promotions, so that the static evaluation is not taken in the middle
+    // of an exchange
+    fn quiescence(
+        &mut self,
+        position: &mut Position,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let in_check = position.in_check();
+        let mut moves = generate_legal_moves(position).as_slice().to_vec();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        // captures that lose material are not worth looking at
+        moves.retain(|mv| mv.promotion.is_some() || (mv.is_capture() && position.see_ge(*mv, 0)));
+        self.order(&mut moves, ply, false);
+
+        for mv in &moves {
+            position.make_move(*mv);
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha);
+            position.unmake_move(*mv);
+
+            if self.stopped {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+}
+
+// iterative deepening alpha-beta search, report is called after every finished iteration. The
+// result is that of the last finished iteration (depth 0 if not even the first one finished)
+pub fn search(
+    position: &Position,
+    limits: SearchLimits,
+    mut report: impl FnMut(&SearchInfo),
+) -> SearchInfo {
+    let mut searcher = Searcher {
+        start: Instant::now(),
+        limits,
+        nodes: 0,
+        stopped: false,
+        previous_pv: Vec::new(),
+        pv: vec![Vec::new(); MAX_PLY + 1],
+    };
+    let mut position = position.clone();
+    let mut best =
+        SearchInfo { depth: 0, score: 0, nodes: 0, time: Duration::ZERO, pv: Vec::new() };
+
+    let max_depth = limits.depth.unwrap_or(MAX_PLY as u32).min(MAX_PLY as u32);
+    for depth in 1..=max_depth {
+        let score = searcher.negamax(&mut position, depth, 0, -INFINITY, INFINITY, true);
+        if searcher.stopped {
+            break;
+        }
+
+        best = SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            time: searcher.start.elapsed(),
+            pv: searcher.pv[0].clone(),
+        };
+        report(&best);
+        searcher.previous_pv = best.pv.clone();
+
+        // nothing left to search for once a mate is found within the full width
+        if best.mate_in().is_some_and(|moves| moves.unsigned_abs() * 2 <= depth) {
+            break;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::*;
+
+    fn best(fen: &str, depth: u32) -> SearchInfo {
+        let position = Position::from_fen(fen).unwrap();
+        search(&position, SearchLimits { depth: Some(depth), time: None }, |_| {})
+    }
+
+    #[test]
+    fn check_search() {
+        // back rank mate
+        let info = best("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 3);
+        assert_eq!(info.best_move().unwrap().to_string(), "d1d8");
+        assert_eq!(info.mate_in(), Some(1));
+
+        // the hanging queen is taken
+        let info = best("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2);
+        assert_eq!(info.best_move().unwrap().to_string(), "d1d5");
+        assert!(info.score > 400);
+
+        // stalemate and checkmate have no moves
+        assert_eq!(best("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 1).pv, []);
+        assert_eq!(best("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", 1).mate_in(), Some(0));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::bitboard::BitBoard;
+use crate::eval::PIECE_VALUES;
+use crate::movegen::legal::en_passant_victim;
+use crate::movegen::*;
+use crate::position::Position;
+use crate::{Colour, Kind, Square};
+
+// large enough that capturing a defended piece with the king never pays off
+const KING_VALUE: i32 = 20000;
+
+fn value(kind: Kind) -> i32 {
+    match kind {
+        Kind::King => KING_VALUE,
+        _ => PIECE_VALUES[kind as usize],
+    }
+}
+
+// cheapest piece of a colour among the attackers
+fn least_valuable(
+    position: &Position,
+    attackers: BitBoard,
+    colour: Colour,
+) -> Option<(Square, Kind)> {
+    [Kind::Pawn, Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen, Kind::King]
+        .into_iter()
+        .find_map(|kind| (attackers & position.pieces_of(colour, kind)).lsb().map(|sq| (sq, kind)))
+}
+
+// static exchange evaluation: material won by the move when both sides keep capturing on its
+// destination with their least valuable attacker and may stop whenever that is better for them.
+// Sliders behind the capturing pieces join in as the pieces in front leave. Pins are ignored
+pub fn see(position: &Position, mv: Move) -> i32 {
+    if mv.flag == MoveFlag::Castle {
+        return 0;
+    }
+
+    let colour = position.piece_at(mv.from).map_or(position.side_to_move, |piece| piece.colour);
+    let both = |kind: Kind| {
+        position.pieces_of(Colour::White, kind) | position.pieces_of(Colour::Black, kind)
+    };
+    let diagonal = both(Kind::Bishop) | both(Kind::Queen);
+    let straight = both(Kind::Rook) | both(Kind::Queen);
+
+    let mut occ = position.occupancy[2] & !BitBoard::from_sq(mv.from);
+    if mv.flag == MoveFlag::EnPassant {
+        occ &= !BitBoard::from_sq(en_passant_victim(&mv));
+    }
+
+    // gain[d] is the material balance for the side making the d-th capture
+    let mut gain = vec![mv.capture.map_or(0, value)];
+    let mut on_square = value(mv.promotion.unwrap_or(mv.piece));
+    if let Some(kind) = mv.promotion {
+        gain[0] += value(kind) - value(Kind::Pawn);
+    }
+
+    let mut attackers = position.attackers_to(mv.to, occ) & occ;
+    let mut side = colour.opposite();
+
+    while let Some((sq, kind)) = least_valuable(position, attackers, side) {
+        // capturing does not pay off even if it is not answered
+        let last = gain[gain.len() - 1];
+        if (-last).max(on_square - last) < 0 {
+            break;
+        }
+        gain.push(on_square - last);
+        on_square = value(kind);
+
+        occ &= !BitBoard::from_sq(sq);
+        if matches!(kind, Kind::Pawn | Kind::Bishop | Kind::Queen) {
+            attackers |= bishop(mv.to, occ) & diagonal;
+        }
+        if matches!(kind, Kind::Rook | Kind::Queen) {
+            attackers |= rook(mv.to, occ) & straight;
+        }
+        attackers &= occ;
+        side = side.opposite();
+    }
+
+    // every side picks between capturing and standing pat, starting from the last capture
+    while gain.len() > 1 {
+        let last = gain.pop().expect("more than one capture");
+        let previous = gain.last_mut().expect("more than one capture");
+        *previous = -(-*previous).max(last);
+    }
+    gain[0]
+}
+
+// check if the exchange started by the move wins at least the threshold
+pub fn see_ge(position: &Position, mv: Move, threshold: i32) -> bool {
+    see(position, mv) >= threshold
+}
+
+// check if the opponent of the piece on the square wins material by capturing it, whoever is to
+// move
+pub fn is_hanging(position: &Position, sq: Square) -> bool {
+    let piece = match position.piece_at(sq) {
+        Some(piece) => piece,
+        None => return false,
+    };
+    let enemy = piece.colour.opposite();
+    let attackers =
+        position.attackers_to(sq, position.occupancy[2]) & position.occupancy[enemy as usize];
+
+    for from in attackers {
+        let kind = position.piece_at(from).expect("attacker is on the board").kind;
+        if see(position, Move::new(from, sq, kind, Some(piece.kind))) > 0 {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::see::*;
+
+    fn see_of(fen: &str, san: &str) -> i32 {
+        let position = Position::from_fen(fen).unwrap();
+        see(&position, position.parse_san(san).unwrap())
+    }
+
+    #[test]
+    fn check_see() {
+        // undefended pawn
+        assert_eq!(see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "Rxe5"), 100);
+        // knight takes a pawn defended twice, with a rook and queen behind the knight
+        assert_eq!(
+            see_of("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "Nxe5"),
+            -220
+        );
+        // pawn defended by a pawn
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "Rxe5"), -400);
+        // the queen x-rays through the rook on the same file
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/4QK2 w - - 0 1", "Rxe5"), 100);
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/5K2 w - - 0 1", "Rxe5"), -400);
+        // and the bishop through the queen on the diagonal
+        assert_eq!(see_of("4k3/8/8/3r4/8/1Q6/B7/4K3 w - - 0 1", "Qxd5"), 500);
+        assert_eq!(see_of("4k3/3r4/8/3r4/8/1Q6/B7/4K3 w - - 0 1", "Qxd5"), 100);
+        assert_eq!(see_of("4k3/3r4/8/3r4/8/1Q6/8/4K3 w - - 0 1", "Qxd5"), -400);
+        // en passant and a promotion
+        assert_eq!(see_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6"), 100);
+        assert_eq!(see_of("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=Q"), 800);
+        // the king cannot take a defended piece
+        assert_eq!(see_of("4k3/8/8/8/8/8/3p4/3K1r2 w - - 0 1", "Kxd2"), 100);
+        let position = Position::from_fen("4k3/8/8/8/8/4p3/3p4/3K4 w - - 0 1").unwrap();
+        let mv = Move::new(Square::D1, Square::D2, Kind::King, Some(Kind::Pawn));
+        assert_eq!(see(&position, mv), 100 - KING_VALUE);
+
+        let position = Position::from_fen("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1").unwrap();
+        let mv = position.parse_san("Rxe5").unwrap();
+        assert!(see_ge(&position, mv, -400) && !see_ge(&position, mv, 0));
+        // a quiet move into an attack loses the piece
+        assert_eq!(see_of("4k3/8/8/8/3p4/8/8/2B1K3 w - - 0 1", "Be3"), -330);
+        assert_eq!(see_of("4k3/8/8/8/3p4/8/8/2B1K3 w - - 0 1", "Bf4"), 0);
+    }
+
+    #[test]
+    fn check_hanging() {
+        let position = Position::from_fen("4k3/8/3p4/4n3/2B5/8/8/4K2R w - - 0 1").unwrap();
+        assert!(!position.is_hanging(Square::E5));
+        assert!(position.is_hanging(Square::C4));
+        assert!(!position.is_hanging(Square::H1));
+        assert!(!position.is_hanging(Square::A1));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::Colour;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+// to represent squares, numbered from a8 (0) to h1 (63) like the bits of a bitboard
+#[rustfmt::skip]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Square {
+    A8, B8, C8, D8, E8, F8, G8, H8,
+    A7, B7, C7, D7, E7, F7, G7, H7,
+    A6, B6, C6, D6, E6, F6, G6, H6,
+    A5, B5, C5, D5, E5, F5, G5, H5,
+    A4, B4, C4, D4, E4, F4, G4, H4,
+    A3, B3, C3, D3, E3, F3, G3, H3,
+    A2, B2, C2, D2, E2, F2, G2, H2,
+    A1, B1, C1, D1, E1, F1, G1, H1,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+// ranks counted from white's side, First is where the white pieces start
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+// a string that is not a square name, holding the input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSquareError(pub String);
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not a square", self.0)
+    }
+}
+
+impl Error for ParseSquareError {}
+
+impl File {
+    pub const ALL: [File; 8] =
+        [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    pub fn from_index(index: usize) -> Option<File> {
+        File::ALL.get(index).copied()
+    }
+    // lowercase letter, a to h
+    pub fn from_char(c: char) -> Option<File> {
+        match c {
+            'a'..='h' => File::from_index(c as usize - 'a' as usize),
+            _ => None,
+        }
+    }
+    pub fn to_char(self) -> char {
+        (b'a' + self as u8) as char
+    }
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [
+        Rank::First,
+        Rank::Second,
+        Rank::Third,
+        Rank::Fourth,
+        Rank::Fifth,
+        Rank::Sixth,
+        Rank::Seventh,
+        Rank::Eighth,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Rank> {
+        Rank::ALL.get(index).copied()
+    }
+    // digit, 1 to 8
+    pub fn from_char(c: char) -> Option<Rank> {
+        match c {
+            '1'..='8' => Rank::from_index(c as usize - '1' as usize),
+            _ => None,
+        }
+    }
+    pub fn to_char(self) -> char {
+        (b'1' + self as u8) as char
+    }
+    // the same rank seen from black's side (First <-> Eighth)
+    pub fn flip(self) -> Rank {
+        Rank::ALL[7 - self as usize]
+    }
+}
+
+#[rustfmt::skip]
+impl Square {
+    // every square in index order, a8 to h1
+    pub const ALL: [Square; 64] = [
+        Square::A8, Square::B8, Square::C8, Square::D8, Square::E8, Square::F8, Square::G8, Square::H8,
+        Square::A7, Square::B7, Square::C7, Square::D7, Square::E7, Square::F7, Square::G7, Square::H7,
+        Square::A6, Square::B6, Square::C6, Square::D6, Square::E6, Square::F6, Square::G6, Square::H6,
+        Square::A5, Square::B5, Square::C5, Square::D5, Square::E5, Square::F5, Square::G5, Square::H5,
+        Square::A4, Square::B4, Square::C4, Square::D4, Square::E4, Square::F4, Square::G4, Square::H4,
+        Square::A3, Square::B3, Square::C3, Square::D3, Square::E3, Square::F3, Square::G3, Square::H3,
+        Square::A2, Square::B2, Square::C2, Square::D2, Square::E2, Square::F2, Square::G2, Square::H2,
+        Square::A1, Square::B1, Square::C1, Square::D1, Square::E1, Square::F1, Square::G1, Square::H1,
+    ];
+}
+
+impl Square {
+    pub fn new(file: File, rank: Rank) -> Square {
+        Square::ALL[(7 - rank as usize) * 8 + file as usize]
+    }
+    pub fn from_index(index: usize) -> Option<Square> {
+        Square::ALL.get(index).copied()
+    }
+    pub fn file(self) -> File {
+        File::ALL[self as usize % 8]
+    }
+    pub fn rank(self) -> Rank {
+        Rank::ALL[7 - self as usize / 8]
+    }
+    // rank from the given side's point of view, eg. e7 is on the second rank for black
+    pub fn relative_rank(self, colour: Colour) -> Rank {
+        match colour {
+            Colour::Black => self.rank().flip(),
+            _ => self.rank(),
+        }
+    }
+    // the square on the same file seen from the other side (e2 <-> e7)
+    pub fn flip(self) -> Square {
+        Square::ALL[self as usize ^ 56]
+    }
+    // the square on the same rank mirrored left to right (b1 <-> g1)
+    pub fn mirror(self) -> Square {
+        Square::ALL[self as usize ^ 7]
+    }
+    // number of king steps between the squares
+    pub fn distance(self, other: Square) -> u32 {
+        let (files, ranks) = self.deltas(other);
+        files.max(ranks)
+    }
+    // number of rook steps of one square between the squares
+    pub fn manhattan_distance(self, other: Square) -> u32 {
+        let (files, ranks) = self.deltas(other);
+        files + ranks
+    }
+    // square the given number of files to the right and ranks up (towards the 8th rank) from
+    // white's side, None if that is off the board
+    pub fn offset(self, files: i32, ranks: i32) -> Option<Square> {
+        let file = File::from_index(usize::try_from(self.file() as i32 + files).ok()?)?;
+        let rank = Rank::from_index(usize::try_from(self.rank() as i32 + ranks).ok()?)?;
+        Some(Square::new(file, rank))
+    }
+
+    fn deltas(self, other: Square) -> (u32, u32) {
+        let files = (self.file() as i32 - other.file() as i32).unsigned_abs();
+        let ranks = (self.rank() as i32 - other.rank() as i32).unsigned_abs();
+        (files, ranks)
+    }
+}
+
+// lowercase algebraic notation, eg. e4
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file().to_char(), self.rank().to_char())
+    }
+}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Square, ParseSquareError> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(File::from_char), chars.next().and_then(Rank::from_char)) {
+            (Some(file), Some(rank)) if chars.next().is_none() => Ok(Square::new(file, rank)),
+            _ => Err(ParseSquareError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::square::*;
+
+    #[test]
+    fn check_square_coordinates() {
+        assert_eq!(Square::from_index(36), Some(Square::E4));
+        assert_eq!(Square::from_index(64), None);
+        assert!(Square::ALL.iter().enumerate().all(|(i, sq)| *sq as usize == i));
+
+        assert_eq!(Square::E4.file(), File::E);
+        assert_eq!(Square::E4.rank(), Rank::Fourth);
+        assert_eq!(Square::new(File::H, Rank::First), Square::H1);
+        assert_eq!(Square::E7.relative_rank(Colour::Black), Rank::Second);
+        assert_eq!(Square::E7.relative_rank(Colour::White), Rank::Seventh);
+
+        assert_eq!(Square::E2.flip(), Square::E7);
+        assert_eq!(Square::B1.mirror(), Square::G1);
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::A1.manhattan_distance(Square::H8), 14);
+        assert_eq!(Square::C3.distance(Square::E4), 2);
+
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::H8.offset(1, 0), None);
+        assert_eq!(Square::A1.offset(0, -1), None);
+    }
+
+    #[test]
+    fn check_square_names() {
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!(Square::A8.to_string(), "a8");
+        assert_eq!("h1".parse(), Ok(Square::H1));
+        assert!(Square::ALL.iter().all(|sq| sq.to_string().parse() == Ok(*sq)));
+
+        for bad in ["", "e", "i4", "e9", "E4", "e44"] {
+            assert_eq!(bad.parse::<Square>(), Err(ParseSquareError(bad.to_string())));
+        }
+    }
+}
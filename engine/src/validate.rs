@@ -0,0 +1,220 @@
+use crate::bitboard::*;
+use crate::movegen::*;
+use crate::position::Position;
+use crate::{Colour, Kind, Piece, Rank, Square};
+use std::fmt;
+
+// a reason why a position cannot arise in a legal game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    // every side needs exactly one king
+    KingCount { colour: Colour, count: u32 },
+    // pawns never stand on the first or last rank
+    PawnOnBackRank(Square),
+    TooManyPawns { colour: Colour, count: u32 },
+    TooManyPieces { colour: Colour, count: u32 },
+    // more promoted pieces than missing pawns
+    TooManyPromoted { colour: Colour, count: u32 },
+    // the side that just moved cannot have left its king in check
+    OpponentInCheck,
+    // no move gives check with more than two pieces at once
+    TooManyCheckers(u32),
+    // a double check always involves a slider uncovered by the move
+    ImpossibleDoubleCheck,
+    // the en passant square does not match a pawn that just moved two squares
+    InvalidEnPassant(Square),
+    // castling right (index into castling_id) without the king and rook on their home squares
+    InvalidCastling(usize),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::KingCount { colour, count } => write!(f, "{:?} has {} kings", colour, count),
+            Problem::PawnOnBackRank(sq) => write!(f, "pawn on {}", sq),
+            Problem::TooManyPawns { colour, count } => {
+                write!(f, "{:?} has {} pawns", colour, count)
+            }
+            Problem::TooManyPieces { colour, count } => {
+                write!(f, "{:?} has {} pieces", colour, count)
+            }
+            Problem::TooManyPromoted { colour, count } => {
+                write!(f, "{:?} has {} promoted pieces but not enough missing pawns", colour, count)
+            }
+            Problem::OpponentInCheck => write!(f, "the side not to move is in check"),
+            Problem::TooManyCheckers(count) => write!(f, "king attacked by {} pieces", count),
+            Problem::ImpossibleDoubleCheck => write!(f, "double check without a slider"),
+            Problem::InvalidEnPassant(sq) => {
+                write!(f, "en passant square {} without a double pawn push", sq)
+            }
+            Problem::InvalidCastling(i) => {
+                let right =
+                    ["white kingside", "white queenside", "black kingside", "black queenside"];
+                write!(f, "{} castling without king and rook on their squares", right[*i])
+            }
+        }
+    }
+}
+
+// check the position for everything that makes it unreachable, reporting all problems found
+pub fn validate(position: &Position) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for colour in [Colour::White, Colour::Black] {
+        material(position, colour, &mut problems);
+    }
+
+    let back_pawns = (position.pieces_of(Colour::White, Kind::Pawn)
+        | position.pieces_of(Colour::Black, Kind::Pawn))
+        & (RANKS[0] | RANKS[7]);
+    problems.extend(back_pawns.into_iter().map(Problem::PawnOnBackRank));
+
+    // the rest needs both kings on the board
+    if problems.iter().any(|problem| matches!(problem, Problem::KingCount { .. })) {
+        return problems;
+    }
+
+    checks(position, &mut problems);
+    en_passant(position, &mut problems);
+    castling(position, &mut problems);
+
+    problems
+}
+
+fn material(position: &Position, colour: Colour, problems: &mut Vec<Problem>) {
+    let count = |kind| position.pieces_of(colour, kind).count_bits();
+
+    let kings = count(Kind::King);
+    if kings != 1 {
+        problems.push(Problem::KingCount { colour, count: kings });
+    }
+
+    let pawns = count(Kind::Pawn);
+    if pawns > 8 {
+        problems.push(Problem::TooManyPawns { colour, count: pawns });
+    }
+
+    let pieces = position.occupancy[colour as usize].count_bits();
+    if pieces > 16 {
+        problems.push(Problem::TooManyPieces { colour, count: pieces });
+    }
+
+    // pieces beyond the starting set must have come from pawns
+    let promoted = count(Kind::Queen).saturating_sub(1)
+        + count(Kind::Rook).saturating_sub(2)
+        + count(Kind::Bishop).saturating_sub(2)
+        + count(Kind::Knight).saturating_sub(2);
+    if promoted > 8u32.saturating_sub(pawns) {
+        problems.push(Problem::TooManyPromoted { colour, count: promoted });
+    }
+}
+
+fn checks(position: &Position, problems: &mut Vec<Problem>) {
+    let side = position.side_to_move;
+    let enemy = side.opposite();
+    let occ = position.occupancy[2];
+
+    let king = position.king_square(enemy);
+    if king.is_some_and(|king| is_attacked_by(king, side, &position.bitboards, occ)) {
+        problems.push(Problem::OpponentInCheck);
+    }
+
+    let checkers = position.checkers();
+    let sliders = position.pieces_of(enemy, Kind::Bishop)
+        | position.pieces_of(enemy, Kind::Rook)
+        | position.pieces_of(enemy, Kind::Queen);
+
+    match checkers.count_bits() {
+        0 | 1 => {}
+        2 if !(checkers & sliders).is_empty() => {}
+        2 => problems.push(Problem::ImpossibleDoubleCheck),
+        count => problems.push(Problem::TooManyCheckers(count)),
+    }
+}
+
+fn en_passant(position: &Position, problems: &mut Vec<Problem>) {
+    let ep = match position.en_passant {
+        Some(ep) => ep,
+        None => return,
+    };
+
+    if !en_passant_possible(position, ep) {
+        problems.push(Problem::InvalidEnPassant(ep));
+    }
+}
+
+// the pawn that just moved passed over the en passant square, which is on the 6th rank (from the
+// side to move's point of view) with the pawn in front of it and its start empty
+pub fn en_passant_possible(position: &Position, ep: Square) -> bool {
+    let side = position.side_to_move;
+    let behind = if side == Colour::White { -1 } else { 1 };
+
+    ep.relative_rank(side) == Rank::Sixth && {
+        let pawn = ep.offset(0, behind).expect("not on the last rank");
+        let start = ep.offset(0, -behind).expect("not on the first rank");
+
+        position.piece_at(pawn) == Some(Piece::new(Kind::Pawn, side.opposite()))
+            && position.piece_at(ep).is_none()
+            && position.piece_at(start).is_none()
+    }
+}
+
+fn castling(position: &Position, problems: &mut Vec<Problem>) {
+    for right in 0..4 {
+        if position.castling_id[right].is_some() && !position.castling_intact(right) {
+            problems.push(Problem::InvalidCastling(right));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen;
+    use crate::validate::*;
+
+    fn problems(fen: &str) -> Vec<Problem> {
+        Position::from_fen(fen).unwrap().validate()
+    }
+
+    #[test]
+    fn check_valid_positions() {
+        for fen in [fen::START_POS, fen::TRICKY_POS, fen::CMK_POS] {
+            assert_eq!(problems(fen), []);
+        }
+    }
+
+    #[test]
+    fn check_invalid_positions() {
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4K2K w - - 0 1"),
+            [Problem::KingCount { colour: Colour::White, count: 2 }]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+            [Problem::PawnOnBackRank(Square::A1)]
+        );
+        assert_eq!(problems("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), []);
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4K2R w - - 0 1"), []);
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), [Problem::OpponentInCheck]);
+        assert_eq!(problems("4k3/3P4/5N2/8/8/8/8/4K3 b - - 0 1"), [Problem::ImpossibleDoubleCheck]);
+        assert_eq!(problems("4k3/8/3N4/1B6/8/8/4R3/4K3 b - - 0 1"), [Problem::TooManyCheckers(3)]);
+
+        // FEN parsing drops such a square, so set it by hand
+        let mut position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        position.en_passant = Some(Square::E3);
+        assert_eq!(position.validate(), [Problem::InvalidEnPassant(Square::E3)]);
+
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4K3 w K - 0 1"), [Problem::InvalidCastling(0)]);
+        assert_eq!(
+            problems("7k/8/8/8/8/7K/NNN5/NNNNNNNN w - - 0 1"),
+            [Problem::TooManyPromoted { colour: Colour::White, count: 9 }]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/PPPPPPPP/PPPPPPPP/4K3 w - - 0 1"),
+            [
+                Problem::TooManyPawns { colour: Colour::White, count: 16 },
+                Problem::TooManyPieces { colour: Colour::White, count: 17 }
+            ]
+        );
+    }
+}
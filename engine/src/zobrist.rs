@@ -0,0 +1,113 @@
+use crate::bitboard::BitBoard;
+use crate::movegen::pawn;
+use crate::{Colour, Kind, Square};
+
+// random keys for every hashed feature of a position
+pub struct Keys {
+    // indexed by [colour][kind][square]
+    pub pieces: [[[u64; 64]; 6]; 2],
+    // indexed by [colour][kind][count], a legal position has at most 10 pieces of one kind
+    pub material: [[[u64; 11]; 6]; 2],
+    pub side: u64,
+    // one key per castling right, same order as castling_id
+    pub castling: [u64; 4],
+    pub en_passant: [u64; 8],
+}
+
+// splitmix64, so the keys are the same on every run and platform
+const fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+const fn generate() -> Keys {
+    let mut state = 0x6b616765; // "kage"
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        material: [[[0; 11]; 6]; 2],
+        side: 0,
+        castling: [0; 4],
+        en_passant: [0; 8],
+    };
+
+    let mut colour = 0;
+    while colour < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut sq = 0;
+            while sq < 64 {
+                keys.pieces[colour][kind][sq] = next(&mut state);
+                sq += 1;
+            }
+            kind += 1;
+        }
+        colour += 1;
+    }
+
+    keys.side = next(&mut state);
+
+    let mut i = 0;
+    while i < 4 {
+        keys.castling[i] = next(&mut state);
+        i += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        keys.en_passant[file] = next(&mut state);
+        file += 1;
+    }
+
+    let mut colour = 0;
+    while colour < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut n = 0;
+            while n < 11 {
+                keys.material[colour][kind][n] = next(&mut state);
+                n += 1;
+            }
+            kind += 1;
+        }
+        colour += 1;
+    }
+
+    keys
+}
+
+pub static KEYS: Keys = generate();
+
+pub fn piece(colour: Colour, kind: Kind, sq: Square) -> u64 {
+    KEYS.pieces[colour as usize][kind as usize][sq as usize]
+}
+
+// key for the nth piece of a kind (n starting at 0) in the material signature, counts above 10
+// only occur in illegal setups and wrap around
+pub fn material(colour: Colour, kind: Kind, n: u32) -> u64 {
+    KEYS.material[colour as usize][kind as usize][n as usize % 11]
+}
+
+// the rook files are fixed for the whole game, so only which rights remain is hashed
+pub fn castling(castling_id: [Option<usize>; 4]) -> u64 {
+    let mut key = 0;
+    for (i, file) in castling_id.iter().enumerate() {
+        if file.is_some() {
+            key ^= KEYS.castling[i];
+        }
+    }
+    key
+}
+
+// the en passant file is only hashed when a pawn of the side to move could actually capture,
+// otherwise identical positions reached by different move orders would hash differently
+pub fn en_passant(ep: Option<Square>, side: Colour, pawns: BitBoard) -> u64 {
+    match ep {
+        Some(sq) if !(pawn::lookup(sq, side.opposite()) & pawns).is_empty() => {
+            KEYS.en_passant[sq as usize % 8]
+        }
+        _ => 0,
+    }
+}